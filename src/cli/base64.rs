@@ -1,9 +1,12 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, io::Write, str::FromStr};
 
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 
-use crate::{process_decode, process_encode, CmdExecutor};
+use crate::{
+    get_writer, process_decode, process_decode_text, process_encode, process_encode_files,
+    process_encode_text, CmdExecutor, Timings,
+};
 
 use super::verify_file;
 
@@ -18,21 +21,44 @@ pub enum Base64SubCommand {
 
 #[derive(Debug, Parser)]
 pub struct Base64EncodeOpts {
-    #[arg(short, long, value_parser = verify_file, default_value = "-")]
-    pub input: String,
+    /// Input file; with several, each is encoded to `<input>.b64`
+    #[arg(short, long, value_parser = verify_file, default_value = "-", num_args = 1..)]
+    pub input: Vec<String>,
+    /// Use this text as the input instead of reading a file or stdin
+    #[arg(long, conflicts_with = "input")]
+    pub input_text: Option<String>,
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// Encode up to this many inputs at once
+    #[arg(short, long, default_value_t = 1)]
+    pub jobs: usize,
+    /// Print a `data:<mime>;base64,...` URI, always with the standard alphabet
+    #[arg(long, conflicts_with = "format")]
+    pub data_uri: bool,
+    /// MIME type for --data-uri instead of guessing it from the input's extension
+    #[arg(long, requires = "data_uri")]
+    pub mime: Option<String>,
 }
 
 #[derive(Debug, Parser)]
 pub struct Base64DecodeOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    /// Use this text as the input instead of reading a file or stdin
+    #[arg(long, conflicts_with = "input")]
+    pub input_text: Option<String>,
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// Treat the decoded content as text and drop its trailing ASCII whitespace
+    #[arg(long)]
+    pub trim: bool,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Base64Format {
     Standard,
     UrlSafe,
@@ -68,19 +94,155 @@ impl fmt::Display for Base64Format {
     }
 }
 
+impl Base64EncodeOpts {
+    /// `--mime`, else text for `--input-text`, else a guess from the input's extension
+    fn data_uri_mime(&self) -> String {
+        if let Some(mime) = &self.mime {
+            return mime.clone();
+        }
+        if self.input_text.is_some() {
+            return "text/plain;charset=utf-8".to_string();
+        }
+        let input = self.input.first().map(String::as_str).unwrap_or("-");
+        mime_guess::from_path(input)
+            .first_or_octet_stream()
+            .to_string()
+    }
+}
+
 impl CmdExecutor for Base64EncodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let encoded = process_encode(&self.input, self.format)?;
-        println!("{}", encoded);
+        let mut timings = Timings::default();
+        let encoded = match (&self.input_text, self.input.as_slice()) {
+            (Some(text), _) => process_encode_text(text, self.format),
+            (None, [input]) => process_encode(input, self.format, &mut timings)?,
+            (None, inputs) => {
+                if self.data_uri {
+                    anyhow::bail!("--data-uri encodes a single input");
+                }
+                if inputs.iter().any(|input| input == "-") || self.output != "-" {
+                    anyhow::bail!("Several inputs must be files and are each written to <input>.b64, without --output");
+                }
+                for output in process_encode_files(inputs, self.format, self.jobs)? {
+                    println!("{}", output);
+                }
+                return Ok(());
+            }
+        };
+        let encoded = if self.data_uri {
+            format!("data:{};base64,{}", self.data_uri_mime(), encoded)
+        } else {
+            encoded
+        };
+        timings.time("write", || -> anyhow::Result<()> {
+            let mut writer = get_writer(&self.output)?;
+            writeln!(writer, "{}", encoded)?;
+            Ok(())
+        })?;
+        timings.report();
         Ok(())
     }
 }
 
 impl CmdExecutor for Base64DecodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let decoded = process_decode(&self.input, self.format)?;
-        let decoded = String::from_utf8(decoded)?;
-        println!("{}", decoded);
+        let mut timings = Timings::default();
+        let decoded = match &self.input_text {
+            Some(text) => process_decode_text(text, self.format)?,
+            None => process_decode(&self.input, self.format, &mut timings)?,
+        };
+        let decoded = if self.trim {
+            let text = std::str::from_utf8(&decoded).map_err(|_| {
+                anyhow::anyhow!("--trim needs the decoded content to be UTF-8 text")
+            })?;
+            text.trim_end_matches(|c: char| c.is_ascii_whitespace())
+                .as_bytes()
+        } else {
+            &decoded[..]
+        };
+        timings.time("write", || -> anyhow::Result<()> {
+            let mut writer = get_writer(&self.output)?;
+            writer.write_all(decoded)?;
+            Ok(())
+        })?;
+        timings.report();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine as _;
+
+    #[tokio::test]
+    async fn test_encode_input_text() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-hello.b64");
+        let output = output.to_string_lossy();
+        let args = ["encode", "--input-text", "hello", "-o", &output];
+        Base64EncodeOpts::parse_from(args).execute().await?;
+        assert_eq!(std::fs::read_to_string(&*output)?, "aGVsbG8=\n");
+
+        let args = ["encode", "--input-text", "hello", "-i", "Cargo.toml"];
+        assert!(Base64EncodeOpts::try_parse_from(args).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decode_trim() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-trim.txt");
+        let output = output.to_string_lossy();
+        // "hello \n\n"
+        let args = [
+            "decode",
+            "--input-text",
+            "aGVsbG8gCgo=",
+            "--trim",
+            "-o",
+            &output,
+        ];
+        Base64DecodeOpts::parse_from(args).execute().await?;
+        assert_eq!(std::fs::read_to_string(&*output)?, "hello");
+
+        let args = ["decode", "--input-text", "/w==", "--trim", "-o", &output];
+        assert!(Base64DecodeOpts::parse_from(args).execute().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encode_data_uri() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-pixel.uri");
+        let output = output.to_string_lossy();
+        let args = [
+            "encode",
+            "-i",
+            "fixtures/pixel.png",
+            "--data-uri",
+            "-o",
+            &output,
+        ];
+        Base64EncodeOpts::parse_from(args).execute().await?;
+        let uri = std::fs::read_to_string(&*output)?;
+        let payload = uri.trim().strip_prefix("data:image/png;base64,").unwrap();
+        assert_eq!(
+            base64::engine::general_purpose::STANDARD.decode(payload)?,
+            std::fs::read("fixtures/pixel.png")?
+        );
+
+        let args = [
+            "encode",
+            "--input-text",
+            "hi",
+            "--data-uri",
+            "--mime",
+            "text/css",
+        ];
+        assert_eq!(
+            Base64EncodeOpts::parse_from(args).data_uri_mime(),
+            "text/css"
+        );
+        let args = ["encode", "--data-uri", "--format", "urlsafe"];
+        assert!(Base64EncodeOpts::try_parse_from(args).is_err());
         Ok(())
     }
 }
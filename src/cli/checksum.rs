@@ -0,0 +1,128 @@
+use std::{fmt, str::FromStr};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use clap::Parser;
+
+use crate::{process_checksum, CmdExecutor};
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct ChecksumOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(long, default_value = "sha256", value_parser = parse_algo)]
+    pub algo: ChecksumAlgo,
+    /// Print the digest base64-encoded instead of hex
+    #[arg(long)]
+    pub base64: bool,
+    /// Compare against this digest and fail if it differs
+    #[arg(long)]
+    pub verify: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ChecksumAlgo {
+    Sha256,
+    Blake3,
+    Md5,
+}
+
+fn parse_algo(algo: &str) -> Result<ChecksumAlgo, anyhow::Error> {
+    algo.parse()
+}
+
+impl FromStr for ChecksumAlgo {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" => Ok(ChecksumAlgo::Sha256),
+            "blake3" => Ok(ChecksumAlgo::Blake3),
+            "md5" => Ok(ChecksumAlgo::Md5),
+            _ => Err(anyhow::anyhow!("Invalid algorithm")),
+        }
+    }
+}
+
+impl From<ChecksumAlgo> for &'static str {
+    fn from(value: ChecksumAlgo) -> Self {
+        match value {
+            ChecksumAlgo::Sha256 => "sha256",
+            ChecksumAlgo::Blake3 => "blake3",
+            ChecksumAlgo::Md5 => "md5",
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+impl ChecksumOpts {
+    fn encode(&self, digest: &[u8]) -> String {
+        if self.base64 {
+            STANDARD.encode(digest)
+        } else {
+            hex::encode(digest)
+        }
+    }
+
+    /// hex digests compare case-insensitively, base64 ones exactly
+    fn matches(&self, digest: &str, expected: &str) -> bool {
+        let expected = expected.trim();
+        if self.base64 {
+            digest == expected
+        } else {
+            digest.eq_ignore_ascii_case(expected)
+        }
+    }
+}
+
+impl CmdExecutor for ChecksumOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let digest = self.encode(&process_checksum(&self.input, self.algo)?);
+        match &self.verify {
+            Some(expected) if self.matches(&digest, expected) => {
+                println!("{}: OK", self.input);
+            }
+            Some(expected) => anyhow::bail!(
+                "{} checksum of {} is {}, expected {}",
+                self.algo,
+                self.input,
+                digest,
+                expected.trim()
+            ),
+            None => println!("{}  {}", digest, self.input),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checksum_verify() {
+        let digest = process_checksum("fixtures/lines.txt", ChecksumAlgo::Blake3).unwrap();
+        let checksum = |expected: &str| {
+            ChecksumOpts::parse_from([
+                "checksum",
+                "-i",
+                "fixtures/lines.txt",
+                "--algo",
+                "blake3",
+                "--verify",
+                expected,
+            ])
+        };
+        let expected = hex::encode(&digest).to_uppercase();
+        assert!(checksum(&expected).execute().await.is_ok());
+        assert!(checksum(&"0".repeat(64)).execute().await.is_err());
+
+        let opts = ChecksumOpts::parse_from(["checksum", "--base64"]);
+        assert!(opts.matches(&opts.encode(&digest), &STANDARD.encode(&digest)));
+    }
+}
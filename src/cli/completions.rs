@@ -0,0 +1,52 @@
+use std::io::Write;
+
+use clap::{CommandFactory, Parser};
+use clap_complete::Shell;
+
+use crate::{CmdExecutor, Opts};
+
+#[derive(Debug, Parser)]
+pub struct CompletionsOpts {
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+impl CompletionsOpts {
+    fn write_completions(&self, writer: &mut impl Write) {
+        let mut cmd = Opts::command();
+        let name = cmd.get_name().to_string();
+        clap_complete::generate(self.shell, &mut cmd, name, writer);
+    }
+}
+
+impl CmdExecutor for CompletionsOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        self.write_completions(&mut std::io::stdout());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bash_completions() -> anyhow::Result<()> {
+        let opts = CompletionsOpts::parse_from(["completions", "bash"]);
+        let mut buf = Vec::new();
+        opts.write_completions(&mut buf);
+        let script = String::from_utf8(buf)?;
+        for subcommand in [
+            "csv",
+            "genpass",
+            "base64",
+            "text",
+            "http",
+            "crypt",
+            "completions",
+        ] {
+            assert!(script.contains(subcommand), "{}", subcommand);
+        }
+        Ok(())
+    }
+}
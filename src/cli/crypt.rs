@@ -0,0 +1,92 @@
+use std::{fmt, io::Write, str::FromStr};
+
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+
+use crate::{process_decrypt, process_encrypt, CmdExecutor};
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum CryptSubCommand {
+    #[command(about = "Encrypt data with a 32-byte key")]
+    Encrypt(CryptEncryptOpts),
+    #[command(about = "Decrypt data encrypted by `crypt encrypt`")]
+    Decrypt(CryptDecryptOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CryptEncryptOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, value_parser = verify_file)]
+    pub key: String,
+    #[arg(long, default_value = "aes256gcm", value_parser = parse_algo)]
+    pub algo: CryptAlgo,
+    /// Decrypt the ciphertext again and check it matches the input before printing it
+    #[arg(long)]
+    pub verify: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct CryptDecryptOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, value_parser = verify_file)]
+    pub key: String,
+    #[arg(long, default_value = "aes256gcm", value_parser = parse_algo)]
+    pub algo: CryptAlgo,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CryptAlgo {
+    Aes256Gcm,
+    ChaCha20,
+}
+
+fn parse_algo(algo: &str) -> Result<CryptAlgo, anyhow::Error> {
+    algo.parse()
+}
+
+impl FromStr for CryptAlgo {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "aes256gcm" => Ok(CryptAlgo::Aes256Gcm),
+            "chacha20" => Ok(CryptAlgo::ChaCha20),
+            _ => Err(anyhow::anyhow!("Invalid algorithm")),
+        }
+    }
+}
+
+impl From<CryptAlgo> for &'static str {
+    fn from(value: CryptAlgo) -> Self {
+        match value {
+            CryptAlgo::Aes256Gcm => "aes256gcm",
+            CryptAlgo::ChaCha20 => "chacha20",
+        }
+    }
+}
+
+impl fmt::Display for CryptAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+impl CmdExecutor for CryptEncryptOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let encrypted = process_encrypt(&self.input, &self.key, self.algo, self.verify)?;
+        println!("{}", encrypted);
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CryptDecryptOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let decrypted = process_decrypt(&self.input, &self.key, self.algo)?;
+        std::io::stdout().write_all(&decrypted)?;
+        Ok(())
+    }
+}
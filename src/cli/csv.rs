@@ -1,33 +1,231 @@
-use std::{fmt, str::FromStr};
+use std::{
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::Context;
 
 use clap::Parser;
+use enum_dispatch::enum_dispatch;
 
-use crate::CmdExecutor;
+use crate::{
+    dry_run_note, get_writer, process_csv_join, CmdExecutor, CsvTransform, RowRange, Timings,
+};
 
-use super::verify_file;
+use super::{verify_file, verify_path};
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpts {
-    #[arg(short, long, value_parser=verify_file)]
-    pub input: String,
-    #[arg(short, long)] // "output.json".into()
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+    /// Input file; repeat to concatenate several files with the same headers
+    #[arg(short, long, value_parser=verify_file, required = true)]
+    pub input: Vec<String>,
+    /// Format of the input: csv, or json or yaml for an array of objects
+    #[arg(long, value_parser = parse_input_format, default_value = "csv")]
+    pub from: InputFormat,
+    /// Output file, `-` for stdout; defaults to output.<format>
+    #[arg(short, long)]
     pub output: Option<String>,
+    /// Convert each input on its own into `<stem>.<format>` in this directory
+    #[arg(long, value_parser = verify_path, conflicts_with_all = ["output", "describe"])]
+    pub output_dir: Option<PathBuf>,
     #[arg(long, value_parser = parse_format, default_value = "json")]
     pub format: OutputFormat,
-    #[arg(short, long, default_value_t = ',')]
+    /// A single character, or one of the keywords tab, space or pipe
+    #[arg(short, long, value_parser = parse_delimiter, default_value = ",")]
     pub delimiter: char,
     #[arg(long, default_value_t = true)]
     pub header: bool,
+    /// Sort rows by this column, numerically when the values are numbers
+    #[arg(long)]
+    pub sort_by: Option<String>,
+    /// Sort in descending order
+    #[arg(long, requires = "sort_by")]
+    pub desc: bool,
+    /// Drop exact duplicate rows, keeping the first
+    #[arg(long)]
+    pub dedup: bool,
+    /// Drop rows whose values in these columns were seen before
+    #[arg(long, value_delimiter = ',')]
+    pub dedup_by: Vec<String>,
+    /// Keep only these 1-based rows before anything else, e.g. 10-20,25,30-
+    #[arg(long, value_delimiter = ',', value_parser = parse_row_range)]
+    pub rows: Vec<RowRange>,
+    /// Keep at most this many rows after filtering and sorting, 0 for all
+    #[arg(long, default_value_t = 0)]
+    pub limit: usize,
+    /// Skip this many rows after filtering and sorting
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+    /// Treat cells with these values as missing, e.g. NA,N/A,null
+    #[arg(long, value_delimiter = ',')]
+    pub na_values: Vec<String>,
+    /// Turn cells holding a JSON object or array into nested values
+    #[arg(long)]
+    pub parse_json_cells: bool,
+    /// Read csv input as fixed-width columns of these widths, e.g. 10,20,5
+    #[arg(long, value_delimiter = ',')]
+    pub fixed_widths: Vec<usize>,
+    /// Accept csv rows with more or fewer fields than the header instead of failing
+    #[arg(long)]
+    pub flexible: bool,
+    /// Write one JSON object per malformed row to this file, with its line, reason and raw content
+    #[arg(long, requires = "flexible", conflicts_with = "describe")]
+    pub error_report: Option<PathBuf>,
+    /// Remove control characters such as embedded newlines and tabs from every cell
+    #[arg(long)]
+    pub strip_control: bool,
+    /// Merge inputs with different headers, leaving missing cells empty (null in JSON/YAML)
+    #[arg(long)]
+    pub union_columns: bool,
+    /// Swap rows and columns, each header leading the row of its values; csv output only
+    #[arg(long, conflicts_with = "describe")]
+    pub transpose: bool,
+    /// Print rows read and written, columns and throughput to stderr
+    #[arg(long)]
+    pub stats: bool,
+    /// Print each column's type, null and distinct counts and numeric range as JSON instead of converting
+    #[arg(long)]
+    pub describe: bool,
+    /// Show what would be written on stderr instead of writing it
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum CsvSubCommand {
+    #[command(name = "join", about = "Join two CSV files on a key column, as JSON")]
+    Join(CsvJoinOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvJoinOpts {
+    #[arg(long, value_parser = verify_file)]
+    pub left: String,
+    #[arg(long, value_parser = verify_file)]
+    pub right: String,
+    /// The key column, present in both files
+    #[arg(long)]
+    pub on: String,
+    /// Keep left rows without a match, with null right cells
+    #[arg(long)]
+    pub left_join: bool,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+}
+
+impl CsvOpts {
+    /// the inputs to convert together and where each conversion goes: one merged output,
+    /// or with --output-dir one `<stem>.<format>` file per input
+    fn outputs(&self) -> anyhow::Result<Vec<(Vec<String>, String)>> {
+        let Some(dir) = &self.output_dir else {
+            let output = match &self.output {
+                Some(output) => output.clone(),
+                None => format!("output.{}", self.format),
+            };
+            return Ok(vec![(self.input.clone(), output)]);
+        };
+        let mut outputs = Vec::with_capacity(self.input.len());
+        for input in &self.input {
+            let stem = Path::new(input)
+                .file_stem()
+                .filter(|_| input != "-")
+                .with_context(|| format!("--output-dir needs file inputs, got {}", input))?;
+            let output = dir.join(format!("{}.{}", stem.to_string_lossy(), self.format));
+            if outputs.iter().any(|(_, o)| *o == output.to_string_lossy()) {
+                anyhow::bail!("Two inputs would both be written to {}", output.display());
+            }
+            outputs.push((vec![input.clone()], output.to_string_lossy().into_owned()));
+        }
+        Ok(outputs)
+    }
+}
+
+impl CmdExecutor for CsvJoinOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let joined = process_csv_join(&self.left, &self.right, &self.on, self.left_join)?;
+        let mut writer = get_writer(&self.output)?;
+        serde_json::to_writer_pretty(&mut writer, &joined)?;
+        writeln!(writer)?;
+        Ok(())
+    }
 }
 
 impl CmdExecutor for CsvOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let output = if let Some(output) = self.output {
-            output
-        } else {
-            format!("output.{}", self.format)
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+        let outputs = self.outputs()?;
+        let transform = CsvTransform {
+            sort_by: self.sort_by,
+            desc: self.desc,
+            dedup: self.dedup,
+            dedup_by: self.dedup_by,
+            rows: self.rows,
+            offset: self.offset,
+            limit: self.limit,
+            union_columns: self.union_columns,
+            na_values: self.na_values,
+            parse_json_cells: self.parse_json_cells,
+            fixed_widths: self.fixed_widths,
+            strip_control: self.strip_control,
+            transpose: self.transpose,
+            flexible: self.flexible,
+            delimiter: Some(self.delimiter as u8),
         };
-        crate::process_csv(&self.input, output, self.format)?;
+        let mut timings = Timings::default();
+        if self.describe {
+            let report =
+                crate::process_csv_describe(&self.input, self.from, &transform, &mut timings)?;
+            println!("{}", report);
+            timings.report();
+            return Ok(());
+        }
+        let mut bad_rows = Vec::new();
+        for (inputs, output) in outputs {
+            let stats = if self.dry_run {
+                let (content, stats) = crate::process_csv_to_string(
+                    &inputs,
+                    self.from,
+                    self.format,
+                    &transform,
+                    &mut timings,
+                )?;
+                eprintln!("{}", dry_run_note(&output, content.as_bytes()));
+                stats
+            } else {
+                crate::process_csv(
+                    &inputs,
+                    self.from,
+                    output,
+                    self.format,
+                    &transform,
+                    &mut timings,
+                )?
+            };
+            if self.stats {
+                eprintln!("{}", stats);
+            }
+            bad_rows.extend(stats.bad_rows);
+        }
+        if let Some(path) = &self.error_report {
+            if self.dry_run {
+                let report = crate::process_csv_error_report_to_string(&bad_rows)?;
+                eprintln!(
+                    "{}",
+                    dry_run_note(&path.to_string_lossy(), report.as_bytes())
+                );
+            } else {
+                crate::process_csv_error_report(path, &bad_rows)?;
+            }
+        }
+        timings.report();
         Ok(())
     }
 }
@@ -36,17 +234,49 @@ impl CmdExecutor for CsvOpts {
 pub enum OutputFormat {
     Json,
     Yaml,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum InputFormat {
+    Csv,
+    Json,
+    Yaml,
 }
 
 fn parse_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
     format.parse()
 }
 
+fn parse_input_format(format: &str) -> Result<InputFormat, anyhow::Error> {
+    format.parse()
+}
+
+fn parse_row_range(range: &str) -> Result<RowRange, anyhow::Error> {
+    range.parse()
+}
+
+fn parse_delimiter(delimiter: &str) -> Result<char, &'static str> {
+    match delimiter {
+        "tab" => Ok('\t'),
+        "space" => Ok(' '),
+        "pipe" => Ok('|'),
+        _ => {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii() => Ok(c),
+                _ => Err("Delimiter must be a single ASCII character, tab, space or pipe"),
+            }
+        }
+    }
+}
+
 impl From<&OutputFormat> for &'static str {
     fn from(value: &OutputFormat) -> Self {
         match value {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::Csv => "csv",
         }
     }
 }
@@ -56,6 +286,7 @@ impl From<OutputFormat> for &'static str {
         match value {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::Csv => "csv",
         }
     }
 }
@@ -66,6 +297,7 @@ impl FromStr for OutputFormat {
         match s.to_lowercase().as_str() {
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
@@ -76,3 +308,107 @@ impl fmt::Display for OutputFormat {
         write!(f, "{}", Into::<&'static str>::into(*self))
     }
 }
+
+impl From<InputFormat> for &'static str {
+    fn from(value: InputFormat) -> Self {
+        match value {
+            InputFormat::Csv => "csv",
+            InputFormat::Json => "json",
+            InputFormat::Yaml => "yaml",
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(InputFormat::Csv),
+            "json" => Ok(InputFormat::Json),
+            "yaml" | "yml" => Ok(InputFormat::Yaml),
+            _ => Err(anyhow::anyhow!("Invalid input format")),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dry_run_writes_nothing() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-dry-run.json");
+        let _ = std::fs::remove_file(&output);
+        let output = output.to_string_lossy();
+        let args = [
+            "csv",
+            "-i",
+            "assets/juventus.csv",
+            "-o",
+            &output,
+            "--dry-run",
+        ];
+        CsvOpts::parse_from(args).execute().await?;
+        assert!(!std::path::Path::new(&*output).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter("tab"), Ok('\t'));
+        assert_eq!(parse_delimiter("space"), Ok(' '));
+        assert_eq!(parse_delimiter("pipe"), Ok('|'));
+        assert_eq!(parse_delimiter(";"), Ok(';'));
+        assert!(parse_delimiter(";;").is_err());
+        assert!(parse_delimiter("").is_err());
+        assert!(parse_delimiter("§").is_err());
+
+        let opts = CsvOpts::parse_from(["csv", "-i", "assets/juventus.csv", "-d", "tab"]);
+        assert_eq!(opts.delimiter, '\t');
+        assert_eq!(
+            CsvOpts::parse_from(["csv", "-i", "assets/juventus.csv"]).delimiter,
+            ','
+        );
+    }
+
+    #[tokio::test]
+    async fn test_output_dir() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("rcli-output-dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let args = [
+            "csv",
+            "-i",
+            "assets/juventus.csv",
+            "-i",
+            "fixtures/na.csv",
+            "--format",
+            "yaml",
+            "--output-dir",
+            dir.to_str().unwrap(),
+        ];
+        CsvOpts::parse_from(args).execute().await?;
+        let juventus = std::fs::read_to_string(dir.join("juventus.yaml"))?;
+        assert!(juventus.contains("Wojciech Szczesny"));
+        let na = std::fs::read_to_string(dir.join("na.yaml"))?;
+        assert!(na.contains("alice") && !na.contains("Szczesny"));
+
+        let args = [
+            "csv",
+            "-i",
+            "fixtures/na.csv",
+            "-o",
+            "x.json",
+            "--output-dir",
+            ".",
+        ];
+        assert!(CsvOpts::try_parse_from(args).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,30 @@
+use std::io::Write;
+
+use clap::Parser;
+
+use crate::{get_writer, process_decode_auto, CmdExecutor, Timings};
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct DecodeOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+}
+
+impl CmdExecutor for DecodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut timings = Timings::default();
+        let (encoding, decoded) = process_decode_auto(&self.input, &mut timings)?;
+        eprintln!("Detected {}", encoding);
+        timings.time("write", || -> anyhow::Result<()> {
+            let mut writer = get_writer(&self.output)?;
+            writer.write_all(&decoded)?;
+            Ok(())
+        })?;
+        timings.report();
+        Ok(())
+    }
+}
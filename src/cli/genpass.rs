@@ -1,11 +1,21 @@
+use std::io::{BufRead, Write};
+#[cfg(feature = "qr")]
+use std::path::PathBuf;
+
 use clap::Parser;
+use serde::Serialize;
 
-use crate::CmdExecutor;
+use crate::{CmdExecutor, GenPassConfig};
+#[cfg(feature = "clipboard")]
+use tracing::warn;
 use zxcvbn::zxcvbn;
 
+const MAX_ATTEMPTS: usize = 100;
+
 #[derive(Debug, Parser)]
 pub struct GenPassOpts {
-    #[arg(short, long, default_value_t = 16)]
+    /// Password length, or `-` to read it from the first line of stdin
+    #[arg(short, long, value_parser = parse_length, default_value = "16")]
     pub length: u8,
     #[arg(long, default_value_t = true)]
     pub uppercase: bool,
@@ -15,20 +25,269 @@ pub struct GenPassOpts {
     pub number: bool,
     #[arg(long, default_value_t = true)]
     pub symbol: bool,
+    /// Draw symbols from these characters instead of the default !@#$%^&*_
+    #[arg(long)]
+    pub symbols: Option<String>,
+    #[arg(long)]
+    pub no_ambiguous: bool,
+    #[arg(long, default_value_t = 0)]
+    pub min_upper: u8,
+    #[arg(long, default_value_t = 0)]
+    pub min_lower: u8,
+    #[arg(long, default_value_t = 0)]
+    pub min_number: u8,
+    #[arg(long, default_value_t = 0)]
+    pub min_symbol: u8,
+    #[arg(long, conflicts_with = "passphrase")]
+    pub charset: Option<String>,
+    #[arg(long)]
+    pub no_repeats: bool,
+    #[arg(long)]
+    pub passphrase: bool,
+    #[arg(long, default_value_t = 5)]
+    pub words: u8,
+    #[arg(long, default_value = "-")]
+    pub separator: String,
+    #[arg(long)]
+    pub capitalize: bool,
+    #[arg(long)]
+    pub trailing_number: bool,
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub min_score: Option<u8>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub no_newline: bool,
+    /// Seed the RNG for reproducible output; never use for real passwords
+    #[arg(long, hide = true, conflicts_with_all = ["charset", "passphrase"])]
+    pub seed: Option<u64>,
+    /// Copy the password to the clipboard instead of printing it
+    #[cfg(feature = "clipboard")]
+    #[arg(long, conflicts_with = "json")]
+    pub clipboard: bool,
+    #[cfg(feature = "qr")]
+    #[arg(long)]
+    pub qr: bool,
+    #[cfg(feature = "qr")]
+    #[arg(long)]
+    pub qr_file: Option<PathBuf>,
+}
+
+fn parse_length(length: &str) -> Result<u8, String> {
+    if length == "-" {
+        read_length(std::io::stdin().lock())
+    } else {
+        length.parse().map_err(|e| format!("{}", e))
+    }
+}
+
+/// the length on the first line of the reader, which must be a positive integer
+fn read_length(mut reader: impl BufRead) -> Result<u8, String> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read the length from stdin: {}", e))?;
+    match line.trim().parse::<u8>() {
+        Ok(length) if length > 0 => Ok(length),
+        _ => Err(format!(
+            "Length on stdin must be an integer from 1 to 255, got {:?}",
+            line.trim()
+        )),
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GenPassReport<'a> {
+    password: &'a str,
+    entropy_bits: f64,
+    zxcvbn_score: u8,
+}
+
+impl GenPassOpts {
+    fn config(&self) -> GenPassConfig {
+        GenPassConfig {
+            length: self.length,
+            upper: self.uppercase,
+            lower: self.lowercase,
+            number: self.number,
+            symbol: self.symbol,
+            no_ambiguous: self.no_ambiguous,
+            min_upper: self.min_upper,
+            min_lower: self.min_lower,
+            min_number: self.min_number,
+            min_symbol: self.min_symbol,
+            no_repeats: self.no_repeats,
+            symbols: self.symbols.clone(),
+            seed: self.seed,
+        }
+    }
+
+    fn generate(&self) -> anyhow::Result<String> {
+        if let Some(charset) = &self.charset {
+            crate::process_genpass_charset(self.length, charset, self.no_repeats)
+        } else if self.passphrase {
+            crate::process_genpass_phrase(
+                self.words,
+                &self.separator,
+                self.capitalize,
+                self.trailing_number,
+            )
+        } else {
+            crate::process_genpass_config(&self.config())
+        }
+    }
+
+    /// generate until the zxcvbn score reaches `--min-score`, returning the password and its score
+    fn generate_scored(&self) -> anyhow::Result<(String, u8)> {
+        let min_score = self.min_score.unwrap_or(0);
+        for _ in 0..MAX_ATTEMPTS {
+            let password = self.generate()?;
+            let score = zxcvbn(&password, &[])?.score();
+            if score >= min_score {
+                return Ok((password, score));
+            }
+        }
+        anyhow::bail!(
+            "Could not reach a strength score of {} in {} attempts, try a longer password",
+            min_score,
+            MAX_ATTEMPTS
+        )
+    }
+
+    fn entropy(&self) -> f64 {
+        if let Some(charset) = &self.charset {
+            crate::genpass_charset_entropy(self.length, charset)
+        } else if self.passphrase {
+            crate::genpass_phrase_entropy(self.words, self.trailing_number)
+        } else {
+            crate::genpass_entropy(&self.config())
+        }
+    }
+
+    fn write_output(&self, writer: &mut impl Write, output: &str) -> anyhow::Result<()> {
+        if self.no_newline {
+            write!(writer, "{}", output)?;
+        } else {
+            writeln!(writer, "{}", output)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// copy the password to the clipboard if asked and possible, print the output otherwise
+    #[cfg_attr(not(feature = "clipboard"), allow(unused_variables))]
+    fn deliver(&self, writer: &mut impl Write, password: &str, output: &str) -> anyhow::Result<()> {
+        #[cfg(feature = "clipboard")]
+        if self.clipboard {
+            match arboard::Clipboard::new().and_then(|mut c| c.set_text(password)) {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("Clipboard is not available ({}), printing instead", e),
+            }
+        }
+        self.write_output(writer, output)
+    }
+
+    fn report(&self, password: &str, score: u8) -> anyhow::Result<String> {
+        let report = GenPassReport {
+            password,
+            entropy_bits: self.entropy(),
+            zxcvbn_score: score,
+        };
+        Ok(serde_json::to_string(&report)?)
+    }
 }
 
 impl CmdExecutor for GenPassOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let password = crate::process_genpass(
-            self.length,
-            self.uppercase,
-            self.lowercase,
-            self.number,
-            self.symbol,
-        )?;
-        println!("{}", password);
-        let estimate = zxcvbn(&password, &[])?;
-        eprintln!("Estimated strength: {}", estimate.score());
+        let (password, score) = self.generate_scored()?;
+        let output = if self.json {
+            self.report(&password, score)?
+        } else {
+            password.clone()
+        };
+        self.deliver(&mut std::io::stdout(), &password, &output)?;
+        #[cfg(feature = "qr")]
+        {
+            if self.qr {
+                println!("{}", crate::process_qr_render(&password)?);
+            }
+            if let Some(path) = &self.qr_file {
+                crate::process_qr_png(&password, path)?;
+            }
+        }
+        if !self.json {
+            eprintln!("Estimated strength: {}", score);
+            eprintln!("Entropy: {:.1} bits", self.entropy());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genpass_json_report() -> anyhow::Result<()> {
+        // short enough that the score depends on the password rather than always being 4
+        let opts = GenPassOpts::parse_from(["genpass", "--json", "--length", "6"]);
+        let (password, score) = opts.generate_scored()?;
+        assert_eq!(score, zxcvbn(&password, &[])?.score());
+        assert!(score < 4);
+        let report = opts.report(&password, score)?;
+        let value: serde_json::Value = serde_json::from_str(&report)?;
+        assert_eq!(value["password"], password.as_str());
+        assert_eq!(value["entropy_bits"], opts.entropy());
+        assert_eq!(value["zxcvbn_score"], score);
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_min_score() {
+        let opts = GenPassOpts::parse_from(["genpass", "--min-score", "4", "--length", "24"]);
+        let (_, score) = opts.generate_scored().unwrap();
+        assert_eq!(score, 4);
+
+        let opts = GenPassOpts::parse_from(["genpass", "--min-score", "4", "--length", "3"]);
+        assert!(opts.generate_scored().is_err());
+
+        let opts = GenPassOpts::parse_from(["genpass", "--min-score", "4", "--length", "6"]);
+        assert!(opts.generate_scored().is_err());
+    }
+
+    #[test]
+    fn test_genpass_no_newline() -> anyhow::Result<()> {
+        let opts = GenPassOpts::parse_from(["genpass", "--no-newline"]);
+        let password = opts.generate()?;
+        let mut buf = Vec::new();
+        opts.write_output(&mut buf, &password)?;
+        assert_eq!(buf, password.as_bytes());
+        assert!(!buf.ends_with(b"\n"));
+
+        let opts = GenPassOpts::parse_from(["genpass"]);
+        let mut buf = Vec::new();
+        opts.write_output(&mut buf, &password)?;
+        assert!(buf.ends_with(b"\n"));
         Ok(())
     }
+
+    #[test]
+    fn test_genpass_prints_without_clipboard() -> anyhow::Result<()> {
+        let opts = GenPassOpts::parse_from(["genpass"]);
+        let password = opts.generate()?;
+        let mut buf = Vec::new();
+        opts.deliver(&mut buf, &password, &password)?;
+        assert_eq!(buf, format!("{}\n", password).as_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_length() {
+        assert_eq!(read_length(&b"20\n"[..]), Ok(20));
+        assert_eq!(read_length(&b" 8 \nignored\n"[..]), Ok(8));
+        assert!(read_length(&b"0\n"[..]).is_err());
+        assert!(read_length(&b"-3\n"[..]).is_err());
+        assert!(read_length(&b"twenty\n"[..]).is_err());
+        assert!(read_length(&b""[..]).is_err());
+    }
 }
@@ -1,9 +1,15 @@
-use std::path::PathBuf;
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    str::FromStr,
+};
 
+use axum::http::HeaderValue;
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 
-use crate::{process_http_serve, CmdExecutor};
+use crate::{process_http_serve, CmdExecutor, HttpServeConfig};
 
 use super::verify_path;
 
@@ -18,12 +24,159 @@ pub enum HttpSubCommand {
 pub struct HttpServeOpts {
     #[arg(short, long, value_parser = verify_path, default_value = ".")]
     pub dir: PathBuf,
+    #[arg(long, default_value = "127.0.0.1")]
+    pub addr: IpAddr,
     #[arg(short, long, default_value_t = 8080)]
     pub port: u16,
+    #[arg(long)]
+    pub download: bool,
+    /// Don't compress responses even when the client accepts gzip/deflate
+    #[arg(long)]
+    pub no_compression: bool,
+    /// Print one `METHOD path status latency` line per request to stderr
+    #[arg(long)]
+    pub access_log: bool,
+    /// Require HTTP Basic auth with this username (needs --password)
+    #[arg(long, requires = "password")]
+    pub username: Option<String>,
+    #[arg(long, requires = "username")]
+    pub password: Option<String>,
+    /// Accept `PUT /path` uploads into the served directory
+    #[arg(long)]
+    pub allow_upload: bool,
+    /// Largest accepted upload in bytes
+    #[arg(long, default_value_t = 10 * 1024 * 1024)]
+    pub max_upload_size: usize,
+    /// Accept `POST /path` uploads, replacing the file (`overwrite`) or appending to it (`append`)
+    #[arg(long, value_parser = parse_upload_mode)]
+    pub upload_mode: Option<UploadMode>,
+    /// Serve HTTPS with this PEM certificate chain (needs --key)
+    #[arg(long, requires = "key")]
+    pub cert: Option<PathBuf>,
+    /// PEM private key for --cert
+    #[arg(long, requires = "cert")]
+    pub key: Option<PathBuf>,
+    /// Serve the root index.html at / and for paths that don't exist, for single-page apps
+    #[arg(long)]
+    pub spa: bool,
+    /// Allow cross-origin requests from any origin
+    #[arg(long)]
+    pub cors: bool,
+    /// Only allow cross-origin requests from this origin; may be repeated, implies --cors
+    #[arg(long = "cors-origin", value_parser = parse_origin)]
+    pub cors_origins: Vec<HeaderValue>,
+    /// Answer 503 when more than this many requests are in flight
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_connections: Option<u32>,
+    /// Answer every GET with this one file, whatever the request path
+    #[arg(long, conflicts_with_all = ["dir", "allow_upload", "spa"])]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadMode {
+    Overwrite,
+    Append,
+}
+
+fn parse_upload_mode(mode: &str) -> Result<UploadMode, anyhow::Error> {
+    mode.parse()
+}
+
+impl FromStr for UploadMode {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "overwrite" => Ok(UploadMode::Overwrite),
+            "append" => Ok(UploadMode::Append),
+            _ => Err(anyhow::anyhow!("Invalid upload mode")),
+        }
+    }
+}
+
+impl From<UploadMode> for &'static str {
+    fn from(value: UploadMode) -> Self {
+        match value {
+            UploadMode::Overwrite => "overwrite",
+            UploadMode::Append => "append",
+        }
+    }
+}
+
+impl fmt::Display for UploadMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+fn parse_origin(origin: &str) -> Result<HeaderValue, &'static str> {
+    HeaderValue::from_str(origin).map_err(|_| "Invalid origin")
+}
+
+impl HttpServeOpts {
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.addr, self.port)
+    }
 }
 
 impl CmdExecutor for HttpServeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        process_http_serve(self.dir, self.port).await
+        let config = HttpServeConfig {
+            addr: self.socket_addr(),
+            path: self.dir,
+            download: self.download,
+            compression: !self.no_compression,
+            access_log: self.access_log,
+            credentials: self.username.zip(self.password),
+            allow_upload: self.allow_upload,
+            max_upload_size: self.max_upload_size,
+            upload_mode: self.upload_mode,
+            tls: self.cert.zip(self.key),
+            spa: self.spa,
+            cors: self.cors || !self.cors_origins.is_empty(),
+            cors_origins: self.cors_origins,
+            file: self.file,
+            max_connections: self.max_connections.map(|n| n as usize),
+        };
+        process_http_serve(config).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_addr() {
+        let opts = HttpServeOpts::parse_from(["serve", "--addr", "0.0.0.0"]);
+        assert_eq!(opts.socket_addr(), "0.0.0.0:8080".parse().unwrap());
+
+        let opts = HttpServeOpts::parse_from(["serve", "-p", "3000"]);
+        assert_eq!(opts.socket_addr(), "127.0.0.1:3000".parse().unwrap());
+
+        assert!(HttpServeOpts::try_parse_from(["serve", "--addr", "localhost:80"]).is_err());
+    }
+
+    #[test]
+    fn test_tls_needs_cert_and_key() {
+        assert!(HttpServeOpts::try_parse_from(["serve", "--cert", "fixtures/cert.pem"]).is_err());
+        assert!(HttpServeOpts::try_parse_from(["serve", "--key", "fixtures/key.pem"]).is_err());
+        let opts = HttpServeOpts::parse_from([
+            "serve",
+            "--cert",
+            "fixtures/cert.pem",
+            "--key",
+            "fixtures/key.pem",
+        ]);
+        assert!(opts.cert.is_some() && opts.key.is_some());
+    }
+
+    #[test]
+    fn test_upload_mode() {
+        let opts = HttpServeOpts::parse_from(["serve"]);
+        assert_eq!(opts.upload_mode, None);
+        let opts = HttpServeOpts::parse_from(["serve", "--upload-mode", "append"]);
+        assert_eq!(opts.upload_mode, Some(UploadMode::Append));
+        assert!(HttpServeOpts::try_parse_from(["serve", "--upload-mode", "prepend"]).is_err());
     }
 }
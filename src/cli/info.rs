@@ -0,0 +1,110 @@
+use clap::{CommandFactory, Parser};
+
+use crate::{
+    Base64Format, ChecksumAlgo, CmdExecutor, CryptAlgo, InputFormat, Opts, OutputFormat,
+    TextSignFormat,
+};
+
+#[derive(Debug, Parser)]
+pub struct InfoOpts {}
+
+impl InfoOpts {
+    /// version, compiled-in cargo features, subcommands and the formats each one accepts
+    fn report(&self) -> String {
+        let features = [
+            ("clipboard", cfg!(feature = "clipboard")),
+            ("qr", cfg!(feature = "qr")),
+            ("url", cfg!(feature = "url")),
+        ]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+        let subcommands = Opts::command()
+            .get_subcommands()
+            .map(|cmd| cmd.get_name().to_string())
+            .collect::<Vec<_>>();
+        let lines = [
+            (
+                "version",
+                format!("{} ({})", env!("CARGO_PKG_VERSION"), env!("RCLI_GIT_SHA")),
+            ),
+            (
+                "features",
+                if features.is_empty() {
+                    "none".to_string()
+                } else {
+                    features.join(", ")
+                },
+            ),
+            ("subcommands", subcommands.join(", ")),
+            (
+                "csv input",
+                join([InputFormat::Csv, InputFormat::Json, InputFormat::Yaml]),
+            ),
+            (
+                "csv output",
+                join([OutputFormat::Json, OutputFormat::Yaml, OutputFormat::Csv]),
+            ),
+            (
+                "base64",
+                join([Base64Format::Standard, Base64Format::UrlSafe]),
+            ),
+            (
+                "text",
+                join([
+                    TextSignFormat::Blake3,
+                    TextSignFormat::Ed25519,
+                    TextSignFormat::Secp256k1,
+                ]),
+            ),
+            ("crypt", join([CryptAlgo::Aes256Gcm, CryptAlgo::ChaCha20])),
+            (
+                "checksum",
+                join([
+                    ChecksumAlgo::Sha256,
+                    ChecksumAlgo::Blake3,
+                    ChecksumAlgo::Md5,
+                ]),
+            ),
+        ];
+        lines
+            .iter()
+            .map(|(name, value)| format!("{}: {}\n", name, value))
+            .collect()
+    }
+}
+
+fn join<T: Into<&'static str>>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl CmdExecutor for InfoOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        print!("{}", self.report());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_lists_subcommands() {
+        let report = InfoOpts {}.report();
+        let subcommands = report
+            .lines()
+            .find_map(|line| line.strip_prefix("subcommands: "))
+            .unwrap();
+        for name in ["csv", "genpass", "base64", "text", "http", "crypt", "info"] {
+            assert!(subcommands.split(", ").any(|s| s == name), "{}", name);
+        }
+        assert!(report.contains(&format!("version: {}", env!("CARGO_PKG_VERSION"))));
+        assert!(report.contains("csv output: json, yaml, csv\n"));
+    }
+}
@@ -0,0 +1,44 @@
+use std::io::{self, BufRead};
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use clap::Parser;
+
+use crate::{process_kdf, CmdExecutor};
+
+#[derive(Debug, Parser)]
+pub struct KdfOpts {
+    /// The password; read from the first line of stdin if omitted
+    #[arg(short, long)]
+    pub password: Option<String>,
+    /// Base64-encoded salt of at least 8 bytes; a random one is generated and printed if omitted
+    #[arg(short, long)]
+    pub salt: Option<String>,
+}
+
+impl CmdExecutor for KdfOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let password = match self.password {
+            Some(password) => password,
+            None => {
+                let mut line = String::new();
+                io::stdin().lock().read_line(&mut line)?;
+                line.trim_end_matches(['\r', '\n']).to_string()
+            }
+        };
+        let salt = match &self.salt {
+            Some(salt) => Some(
+                STANDARD
+                    .decode(salt.trim())
+                    .context("Salt must be base64")?,
+            ),
+            None => None,
+        };
+        let (salt, key) = process_kdf(&password, salt.as_deref())?;
+        if self.salt.is_none() {
+            eprintln!("salt: {}", STANDARD.encode(&salt));
+        }
+        println!("{}", STANDARD.encode(key));
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use clap::Parser;
+
+use crate::{process_lines, CmdExecutor};
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct LinesOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(long)]
+    pub unique: bool,
+    #[arg(long)]
+    pub sort: bool,
+    #[arg(long)]
+    pub reverse: bool,
+    #[arg(long)]
+    pub number: bool,
+    #[arg(long)]
+    pub grep: Option<String>,
+}
+
+impl CmdExecutor for LinesOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let lines = process_lines(
+            &self.input,
+            self.unique,
+            self.sort,
+            self.reverse,
+            self.number,
+            self.grep.as_deref(),
+        )?;
+        for line in lines {
+            println!("{}", line);
+        }
+        Ok(())
+    }
+}
@@ -1,23 +1,74 @@
 mod base64;
+mod checksum;
+mod completions;
+mod crypt;
 mod csv;
+mod decode;
 mod genpass;
 mod http;
+mod info;
+mod kdf;
+mod lines;
 mod text;
 
-use std::path::{Path, PathBuf};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
 
-use clap::Parser;
+use clap::{ArgAction, Parser};
 use enum_dispatch::enum_dispatch;
+use tracing::level_filters::LevelFilter;
 
-pub use self::{base64::*, csv::*, genpass::*, http::*, text::*};
+pub use self::{
+    base64::*, checksum::*, completions::*, crypt::*, csv::*, decode::*, genpass::*, http::*,
+    info::*, kdf::*, lines::*, text::*,
+};
 
 // rcli csv -i input.csv -o output.csv --header -d ','
 #[derive(Debug, Parser)]
 #[command(name = "rcli", version, author, about, long_about=None)]
 pub struct Opts {
+    #[arg(long, global = true)]
+    pub timing: bool,
+    /// Always gunzip csv and lines inputs instead of detecting gzip from the first bytes
+    #[arg(long, global = true, conflicts_with = "no_gzip")]
+    pub gzip: bool,
+    /// Never gunzip csv and lines inputs, even if they look gzipped
+    #[arg(long, global = true)]
+    pub no_gzip: bool,
+    /// Fail instead of overwriting an existing output file
+    #[arg(long, global = true)]
+    pub no_clobber: bool,
+    /// Log more; repeat for more detail (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// Only log errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// Print name, version and git sha as JSON, for build tooling
+    #[arg(long, hide = true, exclusive = true)]
+    pub version_json: bool,
     #[command(subcommand)]
-    pub cmd: SubCommand,
+    pub cmd: Option<SubCommand>,
 }
+
+impl Opts {
+    /// the default log level from -v/-q; RUST_LOG can still refine it per target
+    pub fn log_level(&self) -> LevelFilter {
+        if self.quiet {
+            return LevelFilter::ERROR;
+        }
+        match self.verbose {
+            0 => LevelFilter::WARN,
+            1 => LevelFilter::INFO,
+            2 => LevelFilter::DEBUG,
+            _ => LevelFilter::TRACE,
+        }
+    }
+}
+
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExecutor)]
 pub enum SubCommand {
@@ -31,13 +82,54 @@ pub enum SubCommand {
     Text(TextSubCommand),
     #[command(subcommand, about = "HTTP server")]
     Http(HttpSubCommand),
+    #[command(
+        subcommand,
+        about = "Encrypt or decrypt with AES-256-GCM or ChaCha20-Poly1305"
+    )]
+    Crypt(CryptSubCommand),
+    #[command(name = "lines", about = "Filter, sort or number the lines of a text")]
+    Lines(LinesOpts),
+    #[command(name = "completions", about = "Print a shell completion script")]
+    Completions(CompletionsOpts),
+    #[command(
+        name = "checksum",
+        about = "Compute or verify a sha256, blake3 or md5 digest"
+    )]
+    Checksum(ChecksumOpts),
+    #[command(name = "decode", about = "Decode hex or base64, detecting which it is")]
+    Decode(DecodeOpts),
+    #[command(
+        name = "info",
+        about = "Show the version, compiled-in features and formats"
+    )]
+    Info(InfoOpts),
+    #[command(
+        name = "kdf",
+        about = "Derive a 32-byte key from a password with Argon2id"
+    )]
+    Kdf(KdfOpts),
 }
 
 fn verify_file(filename: &str) -> Result<String, String> {
-    if Path::new(filename).exists() || filename == "-" {
-        Ok(filename.into())
-    } else {
-        Err("File does not exist".into())
+    if filename == "-" || crate::is_url(filename) {
+        return Ok(filename.into());
+    }
+    match fs::metadata(filename) {
+        Ok(meta) if meta.is_dir() => Err("Is a directory, not a file".into()),
+        Ok(_) => File::open(filename)
+            .map(|_| filename.into())
+            .map_err(|e| file_error(&e)),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Err(file_error(&e)),
+        Err(_) => Err("File does not exist".into()),
+    }
+}
+
+/// the message `verify_file` reports when an input file can't be opened
+fn file_error(e: &io::Error) -> String {
+    match e.kind() {
+        io::ErrorKind::PermissionDenied => "Permission denied".into(),
+        io::ErrorKind::NotFound => "File does not exist".into(),
+        _ => e.to_string(),
     }
 }
 
@@ -54,6 +146,40 @@ fn verify_path(path: &str) -> Result<PathBuf, &'static str> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_log_level() {
+        let level = |args: &[&str]| {
+            let args = ["rcli"].iter().chain(args).chain(&["completions", "bash"]);
+            Opts::parse_from(args).log_level()
+        };
+        assert_eq!(level(&[]), LevelFilter::WARN);
+        assert_eq!(level(&["-v"]), LevelFilter::INFO);
+        assert_eq!(level(&["-vv"]), LevelFilter::DEBUG);
+        assert_eq!(level(&["-v", "-v", "-v", "-v"]), LevelFilter::TRACE);
+        assert_eq!(level(&["-q"]), LevelFilter::ERROR);
+        assert!(Opts::try_parse_from(["rcli", "-v", "-q", "completions", "bash"]).is_err());
+
+        // `text verify -q` hides the result and quietens logging too
+        let opts = Opts::parse_from([
+            "rcli",
+            "text",
+            "verify",
+            "-q",
+            "-k",
+            "fixtures/blake3.txt",
+            "--sig",
+            "x",
+        ]);
+        assert_eq!(opts.log_level(), LevelFilter::ERROR);
+    }
+
+    #[test]
+    fn test_version_json_needs_no_subcommand() {
+        let opts = Opts::parse_from(["rcli", "--version-json"]);
+        assert!(opts.version_json);
+        assert!(opts.cmd.is_none());
+    }
+
     #[test]
     fn test_verify_input_file() {
         assert_eq!(verify_file("Cargo.toml"), Ok("Cargo.toml".into()));
@@ -63,5 +189,44 @@ mod tests {
             verify_file("non-existent-file"),
             Err("File does not exist".into())
         );
+        assert_eq!(verify_file("src"), Err("Is a directory, not a file".into()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_verify_file_permission_denied() -> std::io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join("rcli-verify-file");
+        fs::create_dir_all(&dir)?;
+        let file = dir.join("secret.txt");
+        fs::write(&file, "secret")?;
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o000))?;
+        if File::open(&file).is_ok() {
+            // root reads the file regardless of its mode; test_file_error covers the mapping
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o600))?;
+            eprintln!(
+                "skipping test_verify_file_permission_denied: file modes don't apply to root"
+            );
+            return Ok(());
+        }
+        let result = verify_file(&file.to_string_lossy());
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o600))?;
+        assert_eq!(result, Err("Permission denied".into()));
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o000))?;
+        let result = verify_file(&file.to_string_lossy());
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+        assert_eq!(result, Err("Permission denied".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_error() {
+        let error = |kind| file_error(&io::Error::from(kind));
+        assert_eq!(error(io::ErrorKind::PermissionDenied), "Permission denied");
+        assert_eq!(error(io::ErrorKind::NotFound), "File does not exist");
+        let other = io::Error::other("disk on fire");
+        assert_eq!(file_error(&other), "disk on fire");
     }
 }
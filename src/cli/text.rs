@@ -1,9 +1,21 @@
-use std::{fmt, fs, path::PathBuf, str::FromStr};
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::PathBuf,
+    str::FromStr,
+};
 
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 
-use crate::{process_text_generate, process_text_sign, process_text_verify, CmdExecutor};
+use crate::{
+    get_writer, key_passphrase, process_text_generate, process_text_key_fingerprint,
+    process_text_protect_key, process_text_sign, process_text_sign_embedded,
+    process_text_sign_manifest, process_text_sign_reader, process_text_sign_timestamped,
+    process_text_verify, process_text_verify_embedded, process_text_verify_manifest,
+    process_text_verify_reader, process_text_verify_timestamped, CmdExecutor, SignOptions, TextKey,
+};
 
 use super::{verify_file, verify_path};
 
@@ -20,50 +32,218 @@ pub enum TextSubCommand {
 
 #[derive(Debug, Parser)]
 pub struct TextSignOpts {
-    #[arg(short, long, value_parser = verify_file, default_value = "-")]
-    pub input: String,
-    #[arg(short, long, value_parser = verify_file)]
-    pub key: String,
+    #[arg(short, long, value_parser = verify_file, default_value = "-", num_args = 1..)]
+    pub input: Vec<String>,
+    /// Key file, or `env:VAR` to read the base64-encoded key from an environment variable
+    #[arg(short, long, value_parser = verify_key, required_unless_present = "key_literal")]
+    pub key: Option<String>,
+    #[arg(long, conflicts_with = "key")]
+    pub key_literal: Option<String>,
+    /// How the key file is encoded: raw bytes, base64 or hex
+    #[arg(long, value_parser = parse_key_format, default_value = "raw")]
+    pub key_format: KeyFormat,
     #[arg(long, default_value = "blake3", value_parser = parse_format)]
     pub format: TextSignFormat,
+    /// Derive the Blake3 key from a key file of any length instead of requiring 32 bytes
+    #[arg(long)]
+    pub derive_key: bool,
+    /// Length in bytes of a Blake3 MAC, read from its extendable output
+    #[arg(long, default_value_t = 32)]
+    pub length: usize,
+    /// Signature encoding: base64 (URL-safe, unpadded) or hex
+    #[arg(long, value_parser = parse_sig_encoding, default_value = "base64")]
+    pub sig_encoding: SigEncoding,
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+    /// Output one blob carrying both the message and its signature
+    #[arg(long)]
+    pub embed: bool,
+    /// Bind the current time into the signature, for verify --max-age
+    #[arg(long, conflicts_with = "embed")]
+    pub timestamp: bool,
+    /// Sign this text instead of reading an input
+    #[arg(long, conflicts_with_all = ["input", "embed", "timestamp"])]
+    pub input_text: Option<String>,
 }
 
 #[derive(Debug, Parser)]
 pub struct TextVerifyOpts {
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
-    #[arg(short, long, value_parser = verify_file,)]
-    pub key: String,
+    /// Key file, or `env:VAR` to read the base64-encoded key from an environment variable
+    #[arg(short, long, value_parser = verify_key, required_unless_present = "key_literal")]
+    pub key: Option<String>,
+    #[arg(long, conflicts_with = "key")]
+    pub key_literal: Option<String>,
+    /// How the key file is encoded: raw bytes, base64 or hex
+    #[arg(long, value_parser = parse_key_format, default_value = "raw")]
+    pub key_format: KeyFormat,
     #[arg(long, default_value = "blake3", value_parser = parse_format)]
     pub format: TextSignFormat,
+    /// The signature, or `-` to read it from stdin when the input is a file
+    #[arg(short, long, required_unless_present_any = ["manifest", "sig_file", "embed"])]
+    pub sig: Option<String>,
+    #[arg(long, value_parser = verify_file, conflicts_with = "sig")]
+    pub sig_file: Option<String>,
+    /// Verify every `<signature>  <filename>` line of a manifest from `text sign`
+    #[arg(short, long, value_parser = verify_file, conflicts_with_all = ["sig", "sig_file"])]
+    pub manifest: Option<String>,
+    /// Derive the Blake3 key from a key file of any length instead of requiring 32 bytes
+    #[arg(long)]
+    pub derive_key: bool,
+    /// Length in bytes of the Blake3 MAC, as given to sign
+    #[arg(long, default_value_t = 32)]
+    pub length: usize,
+    /// Signature encoding: base64 (URL-safe, unpadded) or hex
+    #[arg(long, value_parser = parse_sig_encoding, default_value = "base64")]
+    pub sig_encoding: SigEncoding,
+    #[arg(long)]
+    pub show_key: bool,
     #[arg(short, long)]
-    pub sig: String,
+    pub quiet: bool,
+    /// Verify a blob from `text sign --embed` given as the input
+    #[arg(long, conflicts_with_all = ["sig", "sig_file", "manifest"])]
+    pub embed: bool,
+    /// Where to write the message recovered by --embed once it verifies, `-` for stdout
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Verify a `text sign --timestamp` signature and reject it if older than this many seconds
+    #[arg(long, conflicts_with_all = ["manifest", "embed"])]
+    pub max_age: Option<u64>,
+    /// Verify the signature of this text instead of reading an input
+    #[arg(long, conflicts_with_all = ["input", "manifest", "embed", "max_age"])]
+    pub input_text: Option<String>,
 }
 
 #[derive(Debug, Parser)]
 pub struct TextKeyGenerateOpts {
     #[arg(short, long, default_value = "blake3", value_parser = parse_format)]
     pub format: TextSignFormat,
-    #[arg(short, long, value_parser = verify_path)]
+    /// Directory to write the key files to, or `-` to print the keys base64-encoded
+    #[arg(short, long, value_parser = verify_key_output)]
     pub output: PathBuf,
+    /// Overwrite existing key files in the output directory
+    #[arg(long)]
+    pub force: bool,
+    /// Show which key files would be written on stderr instead of writing them
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Encrypt the ed25519 secret key with the passphrase in RCLI_KEY_PASSPHRASE, which sign reads too
+    #[arg(long)]
+    pub protect: bool,
+}
+
+/// how the bytes of a `--key` file are encoded
+#[derive(Debug, Clone, Copy)]
+pub enum KeyFormat {
+    Raw,
+    Base64,
+    Hex,
+}
+
+/// how `text sign` writes signatures and `text verify` reads them
+#[derive(Debug, Clone, Copy)]
+pub enum SigEncoding {
+    Base64,
+    Hex,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum TextSignFormat {
     Blake3,
     Ed25519,
+    Secp256k1,
+}
+
+fn verify_key(key: &str) -> Result<String, String> {
+    if key.starts_with("env:") {
+        Ok(key.into())
+    } else {
+        verify_file(key)
+    }
+}
+
+fn verify_key_output(path: &str) -> Result<PathBuf, &'static str> {
+    if path == "-" {
+        Ok(path.into())
+    } else {
+        verify_path(path)
+    }
 }
 
 fn parse_format(format: &str) -> Result<TextSignFormat, anyhow::Error> {
     format.parse()
 }
 
+fn parse_key_format(format: &str) -> Result<KeyFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for KeyFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "raw" => Ok(KeyFormat::Raw),
+            "base64" => Ok(KeyFormat::Base64),
+            "hex" => Ok(KeyFormat::Hex),
+            _ => Err(anyhow::anyhow!("Invalid key format")),
+        }
+    }
+}
+
+impl From<KeyFormat> for &'static str {
+    fn from(value: KeyFormat) -> Self {
+        match value {
+            KeyFormat::Raw => "raw",
+            KeyFormat::Base64 => "base64",
+            KeyFormat::Hex => "hex",
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
+fn parse_sig_encoding(encoding: &str) -> Result<SigEncoding, anyhow::Error> {
+    encoding.parse()
+}
+
+impl FromStr for SigEncoding {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "base64" => Ok(SigEncoding::Base64),
+            "hex" => Ok(SigEncoding::Hex),
+            _ => Err(anyhow::anyhow!("Invalid signature encoding")),
+        }
+    }
+}
+
+impl From<SigEncoding> for &'static str {
+    fn from(value: SigEncoding) -> Self {
+        match value {
+            SigEncoding::Base64 => "base64",
+            SigEncoding::Hex => "hex",
+        }
+    }
+}
+
+impl fmt::Display for SigEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&'static str>::into(*self))
+    }
+}
+
 impl FromStr for TextSignFormat {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "blake3" => Ok(TextSignFormat::Blake3),
             "ed25519" => Ok(TextSignFormat::Ed25519),
+            "secp256k1" => Ok(TextSignFormat::Secp256k1),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
@@ -74,6 +254,7 @@ impl From<TextSignFormat> for &'static str {
         match value {
             TextSignFormat::Blake3 => "blake3",
             TextSignFormat::Ed25519 => "ed25519",
+            TextSignFormat::Secp256k1 => "secp256k1",
         }
     }
 }
@@ -86,34 +267,371 @@ impl fmt::Display for TextSignFormat {
 
 impl CmdExecutor for TextSignOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let signed = process_text_sign(&self.input, &self.key, self.format)?;
-        println!("{}", signed);
+        let key = TextKey::try_new(self.key.as_deref(), self.key_literal.as_deref())?;
+        let opts = SignOptions {
+            derive_key: self.derive_key,
+            blake3_len: self.length,
+            sig_encoding: self.sig_encoding,
+            key_format: self.key_format,
+        };
+        let signed = if let Some(text) = &self.input_text {
+            let signed = process_text_sign_reader(&mut text.as_bytes(), key, self.format, opts)?;
+            format!("{}\n", signed)
+        } else if let [input] = self.input.as_slice() {
+            let signed = if self.embed {
+                process_text_sign_embedded(input, key, self.format, opts)?
+            } else if self.timestamp {
+                process_text_sign_timestamped(input, key, self.format, opts)?
+            } else {
+                process_text_sign(input, key, self.format, opts)?
+            };
+            format!("{}\n", signed)
+        } else if self.embed || self.timestamp {
+            anyhow::bail!("--embed and --timestamp sign a single input");
+        } else {
+            process_text_sign_manifest(&self.input, key, self.format, opts)?
+        };
+        let mut writer = get_writer(&self.output)?;
+        writer.write_all(signed.as_bytes())?;
         Ok(())
     }
 }
 
 impl CmdExecutor for TextKeyGenerateOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let key = process_text_generate(self.format)?;
-        match self.format {
-            crate::TextSignFormat::Blake3 => {
-                let name = self.output.join("blake3.txt");
-                fs::write(name, &key[0])?;
+        let mut key = process_text_generate(self.format)?;
+        if self.protect {
+            key[0] = process_text_protect_key(self.format, &key[0], &key_passphrase()?)?;
+        }
+        if self.output.as_os_str() == "-" {
+            if self.dry_run {
+                eprintln!("would print the {} key to stdout", self.format);
+                return Ok(());
+            }
+            return self.write_keys(&mut std::io::stdout(), &key);
+        }
+        let names = match self.format {
+            TextSignFormat::Blake3 => vec!["blake3.txt"],
+            TextSignFormat::Ed25519 => vec!["ed25519.sk", "ed25519.pk"],
+            TextSignFormat::Secp256k1 => vec!["secp256k1.sk", "secp256k1.pk"],
+        };
+        let paths = names
+            .iter()
+            .map(|name| self.output.join(name))
+            .collect::<Vec<_>>();
+        let existing = paths.iter().filter(|p| p.exists()).collect::<Vec<_>>();
+        if !existing.is_empty() && !self.force {
+            for path in &existing {
+                eprintln!("would overwrite {}", path.display());
+            }
+            anyhow::bail!("Key files already exist; pass --force to overwrite them");
+        }
+        if self.dry_run {
+            // sizes only: the first line of a key is the key
+            for (path, key) in paths.iter().zip(&key) {
+                eprintln!("would write {} bytes to {}", key.len(), path.display());
             }
-            crate::TextSignFormat::Ed25519 => {
-                let name = &self.output;
-                fs::write(name.join("ed25519.sk"), &key[0])?;
-                fs::write(name.join("ed25519.pk"), &key[1])?;
+            return Ok(());
+        }
+        for (path, key) in paths.iter().zip(&key) {
+            fs::write(path, key)?;
+        }
+        Ok(())
+    }
+}
+
+impl TextKeyGenerateOpts {
+    fn write_keys(&self, writer: &mut impl Write, key: &[Vec<u8>]) -> anyhow::Result<()> {
+        match self.format {
+            TextSignFormat::Blake3 => writeln!(writer, "{}", STANDARD.encode(&key[0]))?,
+            TextSignFormat::Ed25519 | TextSignFormat::Secp256k1 => {
+                writeln!(writer, "sk: {}", STANDARD.encode(&key[0]))?;
+                writeln!(writer, "pk: {}", STANDARD.encode(&key[1]))?;
             }
         }
+        writer.flush()?;
         Ok(())
     }
 }
 
 impl CmdExecutor for TextVerifyOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let verified = process_text_verify(&self.input, &self.key, self.format, &self.sig)?;
-        println!("{}", verified);
+        if self.output.is_some() && !self.embed {
+            anyhow::bail!("--output writes the message recovered by --embed");
+        }
+        let key = TextKey::try_new(self.key.as_deref(), self.key_literal.as_deref())?;
+        let opts = SignOptions {
+            derive_key: self.derive_key,
+            blake3_len: self.length,
+            sig_encoding: self.sig_encoding,
+            key_format: self.key_format,
+        };
+        let verified = if let Some(manifest) = &self.manifest {
+            let results = process_text_verify_manifest(manifest, key, self.format, opts)?;
+            for (input, verified) in &results {
+                if !self.quiet {
+                    match verified {
+                        Ok(true) => println!("{}: OK", input),
+                        Ok(false) => println!("{}: FAILED", input),
+                        Err(reason) => println!("{}: FAILED ({})", input, reason),
+                    }
+                }
+            }
+            results.iter().all(|(_, verified)| *verified == Ok(true))
+        } else if self.embed {
+            let (message, verified) =
+                process_text_verify_embedded(&self.input, key, self.format, opts)?;
+            let output = self.output.as_deref().unwrap_or("-");
+            if verified {
+                let mut writer = get_writer(output)?;
+                writer.write_all(&message)?;
+            }
+            // on stdout the message itself is the result
+            if !self.quiet && output != "-" {
+                println!("{}", verified);
+            }
+            verified
+        } else {
+            let sig = match (&self.sig_file, self.sig.as_deref()) {
+                (Some(sig_file), _) => fs::read_to_string(sig_file)?,
+                (None, Some("-")) => {
+                    if self.input_text.is_none() && self.input == "-" {
+                        anyhow::bail!(
+                            "--sig - reads the signature from stdin, so --input must be a file"
+                        );
+                    }
+                    let mut sig = String::new();
+                    io::stdin().read_to_string(&mut sig)?;
+                    sig
+                }
+                (None, sig) => sig.unwrap_or_default().to_string(),
+            };
+            let verified = match (&self.input_text, self.max_age) {
+                (Some(text), _) => {
+                    process_text_verify_reader(&mut text.as_bytes(), key, self.format, &sig, opts)?
+                }
+                (None, Some(max_age)) => process_text_verify_timestamped(
+                    &self.input,
+                    key,
+                    self.format,
+                    &sig,
+                    opts,
+                    max_age,
+                )?,
+                (None, None) => process_text_verify(&self.input, key, self.format, &sig, opts)?,
+            };
+            if !self.quiet {
+                println!("{}", verified);
+            }
+            verified
+        };
+        if self.show_key {
+            let fingerprint = process_text_key_fingerprint(key, self.format, opts)?;
+            println!("key: {}", fingerprint);
+        }
+        if !verified {
+            anyhow::bail!("Signature verification failed");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_fails_on_tampered_message() -> anyhow::Result<()> {
+        let key = TextKey::Path("fixtures/blake3.txt");
+        let sig = process_text_sign(
+            "fixtures/lines.txt",
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        let sig = format!("--sig={}", sig);
+        let verify = |input| {
+            TextVerifyOpts::parse_from([
+                "verify",
+                "-q",
+                "-k",
+                "fixtures/blake3.txt",
+                "-i",
+                input,
+                &sig,
+            ])
+        };
+
+        assert!(verify("fixtures/lines.txt").execute().await.is_ok());
+        assert!(verify("fixtures/b64.txt").execute().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify_input_text() -> anyhow::Result<()> {
+        let key = TextKey::Path("fixtures/ed25519.sk");
+        let sig = process_text_sign_reader(
+            &mut &b"hello world"[..],
+            key,
+            TextSignFormat::Ed25519,
+            SignOptions::default(),
+        )?;
+        let sig = format!("--sig={}", sig);
+        let verify = |text| {
+            TextVerifyOpts::parse_from([
+                "verify",
+                "-q",
+                "--format",
+                "ed25519",
+                "-k",
+                "fixtures/ed25519.pk",
+                "--input-text",
+                text,
+                &sig,
+            ])
+        };
+        assert!(verify("hello world").execute().await.is_ok());
+        assert!(verify("hello world!").execute().await.is_err());
+        assert!(TextSignOpts::try_parse_from([
+            "sign",
+            "-k",
+            "fixtures/blake3.txt",
+            "-i",
+            "fixtures/lines.txt",
+            "--input-text",
+            "hi"
+        ])
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_from_env_var() {
+        let opts = TextSignOpts::try_parse_from(["sign", "-k", "env:RCLI_KEY"]);
+        assert_eq!(opts.unwrap().key.as_deref(), Some("env:RCLI_KEY"));
+        assert!(TextSignOpts::try_parse_from(["sign", "-k", "no-such-key-file"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sign_to_file_and_verify_sig_file() -> anyhow::Result<()> {
+        let sig_file = std::env::temp_dir().join("rcli-lines.sig");
+        let sig_file = sig_file.to_string_lossy();
+        let key = ["-k", "fixtures/blake3.txt", "-i", "fixtures/lines.txt"];
+
+        let sign = ["sign", "-o", &sig_file];
+        TextSignOpts::parse_from(sign.iter().chain(&key))
+            .execute()
+            .await?;
+        assert!(!fs::read_to_string(sig_file.as_ref())?.trim().is_empty());
+
+        let verify = ["verify", "-q", "--sig-file", &sig_file];
+        TextVerifyOpts::parse_from(verify.iter().chain(&key))
+            .execute()
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_verify_embedded() -> anyhow::Result<()> {
+        let blob = std::env::temp_dir().join("rcli-lines.blob");
+        let blob = blob.to_string_lossy();
+
+        let sign = [
+            "sign",
+            "--embed",
+            "--format",
+            "ed25519",
+            "-k",
+            "fixtures/ed25519.sk",
+        ];
+        TextSignOpts::parse_from(
+            sign.iter()
+                .chain(&["-i", "fixtures/lines.txt", "-o", &blob]),
+        )
+        .execute()
+        .await?;
+
+        let verify = [
+            "verify",
+            "-q",
+            "--embed",
+            "--format",
+            "ed25519",
+            "-k",
+            "fixtures/ed25519.pk",
+        ];
+        let message = std::env::temp_dir().join("rcli-lines.message");
+        let message = message.to_string_lossy();
+        TextVerifyOpts::parse_from(verify.iter().chain(&["-i", &blob, "-o", &message]))
+            .execute()
+            .await?;
+        assert_eq!(fs::read(message.as_ref())?, fs::read("fixtures/lines.txt")?);
+        let plain = [
+            "verify",
+            "-k",
+            "fixtures/blake3.txt",
+            "--sig",
+            "x",
+            "-o",
+            "m",
+        ];
+        let err = TextVerifyOpts::parse_from(plain)
+            .execute()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("--embed"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_to_stdout() -> anyhow::Result<()> {
+        let opts = TextKeyGenerateOpts::parse_from(["generate", "-f", "ed25519", "-o", "-"]);
+        let key = process_text_generate(opts.format)?;
+        let mut buf = Vec::new();
+        opts.write_keys(&mut buf, &key)?;
+        let out = String::from_utf8(buf)?;
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        let sk = lines[0].strip_prefix("sk: ").unwrap();
+        assert_eq!(STANDARD.decode(sk)?, key[0]);
+        let pk = lines[1].strip_prefix("pk: ").unwrap();
+        assert_eq!(STANDARD.decode(pk)?.len(), 32);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_dry_run() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("rcli-keygen-dry-run");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let generate = ["generate", "-f", "ed25519", "--dry-run", "-o"];
+        TextKeyGenerateOpts::parse_from(generate.iter().chain(&[&*dir.to_string_lossy()]))
+            .execute()
+            .await?;
+        assert_eq!(fs::read_dir(&dir)?.count(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_refuses_overwrite() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("rcli-keygen-force");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let dir = dir.to_string_lossy();
+        let generate = ["generate", "-f", "ed25519", "-o", &dir];
+
+        TextKeyGenerateOpts::parse_from(generate).execute().await?;
+        let sk = fs::read(format!("{}/ed25519.sk", dir))?;
+        assert!(TextKeyGenerateOpts::parse_from(generate)
+            .execute()
+            .await
+            .is_err());
+        assert_eq!(fs::read(format!("{}/ed25519.sk", dir))?, sk);
+
+        TextKeyGenerateOpts::parse_from(generate.iter().chain(&["--force"]))
+            .execute()
+            .await?;
+        assert_ne!(fs::read(format!("{}/ed25519.sk", dir))?, sk);
         Ok(())
     }
 }
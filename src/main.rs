@@ -1,12 +1,37 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
 
 use rcli::CmdExecutor;
 use rcli::Opts;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt::init();
     let opts = Opts::parse();
-    opts.cmd.execute().await?;
+    if opts.version_json {
+        println!("{}", rcli::version_json());
+        return Ok(());
+    }
+    let filter = EnvFilter::builder()
+        .with_default_directive(opts.log_level().into())
+        .from_env_lossy();
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+    if opts.timing {
+        rcli::enable_timing();
+    }
+    if opts.gzip || opts.no_gzip {
+        rcli::set_gzip(Some(opts.gzip));
+    }
+    if opts.no_clobber {
+        rcli::set_no_clobber(true);
+    }
+    let Some(cmd) = opts.cmd else {
+        Opts::command()
+            .error(ErrorKind::MissingSubcommand, "a subcommand is required")
+            .exit();
+    };
+    cmd.execute().await?;
     Ok(())
 }
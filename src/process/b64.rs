@@ -1,49 +1,151 @@
 use std::io::Read;
 
-use base64::{
-    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
-    Engine as _,
-};
-
-use crate::{get_reader, Base64Format};
-pub fn process_encode(input: &str, format: Base64Format) -> anyhow::Result<String> {
-    let mut reader = get_reader(input)?;
+use anyhow::Context;
+
+use tracing::info;
+
+use crate::{get_reader, write_atomic, Base64Format, ByteCodec, Timings};
+pub fn process_encode(
+    input: &str,
+    format: Base64Format,
+    timings: &mut Timings,
+) -> anyhow::Result<String> {
+    let buf = timings.time("read", || -> anyhow::Result<Vec<u8>> {
+        let mut reader = get_reader(input)?;
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Ok(buf)
+    })?;
+    let encoded = timings.time("process", || format.encode(&buf));
+    Ok(encoded)
+}
+
+/// encode each input into `<input>.b64`, running up to `jobs` of them at once; the written
+/// paths come back in input order whatever order the encodings finish in
+pub fn process_encode_files(
+    inputs: &[String],
+    format: Base64Format,
+    jobs: usize,
+) -> anyhow::Result<Vec<String>> {
+    let jobs = jobs.clamp(1, inputs.len().max(1));
+    let chunk_size = inputs.len().div_ceil(jobs).max(1);
+    std::thread::scope(|s| {
+        let handles: Vec<_> = inputs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|input| encode_file(input, format))
+                        .collect::<anyhow::Result<Vec<_>>>()
+                })
+            })
+            .collect();
+        let mut written = Vec::with_capacity(inputs.len());
+        for handle in handles {
+            let paths = handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("Encoding thread panicked"))??;
+            written.extend(paths);
+        }
+        Ok(written)
+    })
+}
+
+fn encode_file(input: &str, format: Base64Format) -> anyhow::Result<String> {
+    let mut reader = get_reader(input).with_context(|| format!("Failed to open {}", input))?;
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf)?;
-    let encoded = match format {
-        Base64Format::Standard => STANDARD.encode(&buf),
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.encode(&buf),
-    };
-    Ok(encoded)
+    let output = format!("{}.b64", input);
+    let encoded = format.encode(&buf);
+    write_atomic(&output, |writer| Ok(writeln!(writer, "{}", encoded)?))
+        .with_context(|| format!("Failed to write {}", output))?;
+    info!("Encoded {} to {}", input, output);
+    Ok(output)
+}
+
+/// encode the text itself rather than reading an input
+pub fn process_encode_text(text: &str, format: Base64Format) -> String {
+    format.encode(text.as_bytes())
 }
 
-pub fn process_decode(input: &str, format: Base64Format) -> anyhow::Result<Vec<u8>> {
-    let mut reader = get_reader(input)?;
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf)?;
-    let buf = buf.trim();
-    let decoded = match format {
-        Base64Format::Standard => STANDARD.decode(buf)?,
-        Base64Format::UrlSafe => URL_SAFE_NO_PAD.decode(buf)?,
-    };
+pub fn process_decode(
+    input: &str,
+    format: Base64Format,
+    timings: &mut Timings,
+) -> anyhow::Result<Vec<u8>> {
+    let buf = timings.time("read", || -> anyhow::Result<String> {
+        let mut reader = get_reader(input)?;
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf)?;
+        Ok(buf)
+    })?;
+    let decoded = timings.time("process", || format.decode(&buf))?;
 
     Ok(decoded)
 }
 
+/// decode the text itself rather than reading an input
+pub fn process_decode_text(text: &str, format: Base64Format) -> anyhow::Result<Vec<u8>> {
+    format.decode(text)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use super::*;
 
     #[test]
     fn test_process_encode() {
         let input = "Cargo.toml";
         let format = Base64Format::Standard;
-        assert!(process_encode(input, format).is_ok())
+        assert!(process_encode(input, format, &mut Timings::default()).is_ok())
     }
     #[test]
     fn test_process_decode() {
         let input = "fixtures/b64.txt";
         let format = Base64Format::Standard;
-        assert!(process_decode(input, format).is_ok())
+        assert!(process_decode(input, format, &mut Timings::default()).is_ok())
+    }
+
+    #[test]
+    fn test_process_encode_decode_text() -> anyhow::Result<()> {
+        assert_eq!(
+            process_encode_text("hello", Base64Format::Standard),
+            "aGVsbG8="
+        );
+        assert_eq!(
+            process_encode_text("hello", Base64Format::UrlSafe),
+            "aGVsbG8"
+        );
+        assert_eq!(
+            process_decode_text("aGVsbG8=\n", Base64Format::Standard)?,
+            b"hello"
+        );
+        assert!(process_decode_text("aGVsbG8=", Base64Format::UrlSafe).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_encode_files_matches_single_file() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("rcli-encode-files");
+        fs::create_dir_all(&dir)?;
+        let mut inputs = Vec::new();
+        for name in ["Cargo.toml", "fixtures/lines.txt"] {
+            let copy = dir.join(std::path::Path::new(name).file_name().unwrap());
+            fs::copy(name, &copy)?;
+            inputs.push(copy.to_string_lossy().into_owned());
+        }
+        let written = process_encode_files(&inputs, Base64Format::Standard, 2)?;
+        assert_eq!(
+            written,
+            [format!("{}.b64", inputs[0]), format!("{}.b64", inputs[1])]
+        );
+        for (input, output) in inputs.iter().zip(&written) {
+            let single = process_encode(input, Base64Format::Standard, &mut Timings::default())?;
+            assert_eq!(fs::read_to_string(output)?, format!("{}\n", single));
+        }
+        Ok(())
     }
 }
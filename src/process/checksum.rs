@@ -0,0 +1,67 @@
+use std::io;
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use crate::{get_reader, ChecksumAlgo};
+
+/// hash the input's bytes as they stream in, never decompressed, and return the raw digest
+pub fn process_checksum(input: &str, algo: ChecksumAlgo) -> anyhow::Result<Vec<u8>> {
+    let mut reader = get_reader(input)?;
+    let digest = match algo {
+        ChecksumAlgo::Sha256 => {
+            let mut hasher = Sha256::new();
+            io::copy(&mut reader, &mut hasher)?;
+            hasher.finalize().to_vec()
+        }
+        ChecksumAlgo::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(&mut reader, &mut hasher)?;
+            hasher.finalize().as_bytes().to_vec()
+        }
+        ChecksumAlgo::Md5 => {
+            let mut hasher = Md5::new();
+            io::copy(&mut reader, &mut hasher)?;
+            hasher.finalize().to_vec()
+        }
+    };
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_checksum_known_vectors() -> anyhow::Result<()> {
+        let input = std::env::temp_dir().join("rcli-abc.txt");
+        std::fs::write(&input, "abc")?;
+        let input = input.to_string_lossy();
+        let vectors = [
+            (
+                ChecksumAlgo::Sha256,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                ChecksumAlgo::Blake3,
+                "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            ),
+            (ChecksumAlgo::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+        ];
+        for (algo, expected) in vectors {
+            assert_eq!(hex::encode(process_checksum(&input, algo)?), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_checksum_hashes_gzip_bytes() -> anyhow::Result<()> {
+        // sha256sum fixtures/juventus.csv.gz
+        let digest = process_checksum("fixtures/juventus.csv.gz", ChecksumAlgo::Sha256)?;
+        assert_eq!(
+            hex::encode(digest),
+            "6e010df9513ca579ec20af48655c6375400ef0e2cd55be4fbb47102666223bd1"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+
+use crate::Base64Format;
+
+/// a text encoding of bytes; decoding ignores surrounding whitespace
+pub trait ByteCodec {
+    fn encode(&self, data: &[u8]) -> String;
+    fn decode(&self, data: &str) -> anyhow::Result<Vec<u8>>;
+}
+
+/// base64 with the standard alphabet and padding
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StandardBase64;
+
+/// base64 with the URL-safe alphabet and no padding
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UrlSafeBase64;
+
+/// lowercase hex; decoding accepts either case
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hex;
+
+impl ByteCodec for StandardBase64 {
+    fn encode(&self, data: &[u8]) -> String {
+        STANDARD.encode(data)
+    }
+
+    fn decode(&self, data: &str) -> anyhow::Result<Vec<u8>> {
+        Ok(STANDARD.decode(data.trim())?)
+    }
+}
+
+impl ByteCodec for UrlSafeBase64 {
+    fn encode(&self, data: &[u8]) -> String {
+        URL_SAFE_NO_PAD.encode(data)
+    }
+
+    fn decode(&self, data: &str) -> anyhow::Result<Vec<u8>> {
+        Ok(URL_SAFE_NO_PAD.decode(data.trim())?)
+    }
+}
+
+impl ByteCodec for Hex {
+    fn encode(&self, data: &[u8]) -> String {
+        hex::encode(data)
+    }
+
+    fn decode(&self, data: &str) -> anyhow::Result<Vec<u8>> {
+        Ok(hex::decode(data.trim())?)
+    }
+}
+
+impl Base64Format {
+    /// the codec for this variant
+    pub fn codec(self) -> &'static dyn ByteCodec {
+        match self {
+            Base64Format::Standard => &StandardBase64,
+            Base64Format::UrlSafe => &UrlSafeBase64,
+        }
+    }
+}
+
+impl ByteCodec for Base64Format {
+    fn encode(&self, data: &[u8]) -> String {
+        self.codec().encode(data)
+    }
+
+    fn decode(&self, data: &str) -> anyhow::Result<Vec<u8>> {
+        self.codec().decode(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(codec: &dyn ByteCodec, data: &[u8], encoded: &str) -> anyhow::Result<()> {
+        assert_eq!(codec.encode(data), encoded);
+        assert_eq!(codec.decode(encoded)?, data);
+        assert_eq!(codec.decode(&format!(" {}\n", encoded))?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_standard_base64() -> anyhow::Result<()> {
+        round_trip(&StandardBase64, b"hello", "aGVsbG8=")?;
+        round_trip(&StandardBase64, &[0xfb, 0xff], "+/8=")?;
+        assert!(StandardBase64.decode("-_8").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_safe_base64() -> anyhow::Result<()> {
+        round_trip(&UrlSafeBase64, b"hello", "aGVsbG8")?;
+        round_trip(&UrlSafeBase64, &[0xfb, 0xff], "-_8")?;
+        assert!(UrlSafeBase64.decode("aGVsbG8=").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hex() -> anyhow::Result<()> {
+        round_trip(&Hex, b"hello", "68656c6c6f")?;
+        assert_eq!(Hex.decode("FBFF")?, [0xfb, 0xff]);
+        assert!(Hex.decode("abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_base64_format_codec() -> anyhow::Result<()> {
+        round_trip(&Base64Format::Standard, b"hello", "aGVsbG8=")?;
+        round_trip(&Base64Format::UrlSafe, b"hello", "aGVsbG8")?;
+        Ok(())
+    }
+}
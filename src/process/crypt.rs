@@ -0,0 +1,337 @@
+use std::{fs, io::Read, path::Path};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit},
+    Aes256Gcm,
+};
+use anyhow::Context;
+use argon2::Argon2;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chacha20poly1305::ChaCha20Poly1305;
+use rand::{rngs::OsRng, RngCore};
+
+use super::text::{trim_key_file, KeyLoader};
+use crate::{get_reader, CryptAlgo};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+/// marks a secret key file encrypted by `protect_key`
+pub(super) const PROTECTED_KEY_MAGIC: &[u8] = b"rcli-sk1";
+
+pub trait DataEncrypt {
+    /// encrypt the data from the reader and return the nonce followed by the ciphertext
+    fn encrypt(&self, reader: &mut dyn Read) -> anyhow::Result<Vec<u8>>;
+}
+
+pub trait DataDecrypt {
+    /// split the nonce off the data and decrypt the rest
+    fn decrypt(&self, data: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+pub struct Aes256GcmCipher {
+    cipher: Aes256Gcm,
+}
+
+pub struct ChaCha20Poly1305Cipher {
+    cipher: ChaCha20Poly1305,
+}
+
+/// a cipher that can both encrypt and decrypt, for `process_encrypt`'s self-check
+trait Cipher: DataEncrypt + DataDecrypt {}
+
+impl<T: DataEncrypt + DataDecrypt> Cipher for T {}
+
+/// encrypt the input; with `verify`, decrypt the result again and check it matches the
+/// input before returning it
+pub fn process_encrypt(
+    input: &str,
+    key: &str,
+    algo: CryptAlgo,
+    verify: bool,
+) -> anyhow::Result<String> {
+    let mut reader = get_reader(input)?;
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext)?;
+    let cipher: Box<dyn Cipher> = match algo {
+        CryptAlgo::Aes256Gcm => Box::new(Aes256GcmCipher::load(key)?),
+        CryptAlgo::ChaCha20 => Box::new(ChaCha20Poly1305Cipher::load(key)?),
+    };
+    let encrypted = cipher.encrypt(&mut plaintext.as_slice())?;
+    if verify {
+        check_round_trip(cipher.as_ref(), &plaintext, &encrypted)?;
+    }
+    Ok(URL_SAFE_NO_PAD.encode(encrypted))
+}
+
+pub fn process_decrypt(input: &str, key: &str, algo: CryptAlgo) -> anyhow::Result<Vec<u8>> {
+    let mut reader = get_reader(input)?;
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let data = URL_SAFE_NO_PAD.decode(buf.trim())?;
+    let cipher: Box<dyn DataDecrypt> = match algo {
+        CryptAlgo::Aes256Gcm => Box::new(Aes256GcmCipher::load(key)?),
+        CryptAlgo::ChaCha20 => Box::new(ChaCha20Poly1305Cipher::load(key)?),
+    };
+    cipher.decrypt(&data)
+}
+
+impl DataEncrypt for Aes256GcmCipher {
+    fn encrypt(&self, reader: &mut dyn Read) -> anyhow::Result<Vec<u8>> {
+        seal(&self.cipher, reader)
+    }
+}
+
+impl DataDecrypt for Aes256GcmCipher {
+    fn decrypt(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        open(&self.cipher, data)
+    }
+}
+
+impl DataEncrypt for ChaCha20Poly1305Cipher {
+    fn encrypt(&self, reader: &mut dyn Read) -> anyhow::Result<Vec<u8>> {
+        seal(&self.cipher, reader)
+    }
+}
+
+impl DataDecrypt for ChaCha20Poly1305Cipher {
+    fn decrypt(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        open(&self.cipher, data)
+    }
+}
+
+impl KeyLoader for Aes256GcmCipher {
+    fn load(path: impl AsRef<Path>) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let key = fs::read(path)?;
+        Self::try_new(trim_key_file(&key, KEY_LEN))
+    }
+}
+
+impl Aes256GcmCipher {
+    pub fn new(cipher: Aes256Gcm) -> Self {
+        Self { cipher }
+    }
+    /// the key must be exactly 32 bytes
+    pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
+        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| {
+            anyhow::anyhow!(
+                "AES-256-GCM key must be exactly 32 bytes, got {}",
+                key.len()
+            )
+        })?;
+        Ok(Aes256GcmCipher::new(cipher))
+    }
+}
+
+impl KeyLoader for ChaCha20Poly1305Cipher {
+    fn load(path: impl AsRef<Path>) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let key = fs::read(path)?;
+        Self::try_new(trim_key_file(&key, KEY_LEN))
+    }
+}
+
+impl ChaCha20Poly1305Cipher {
+    pub fn new(cipher: ChaCha20Poly1305) -> Self {
+        Self { cipher }
+    }
+    /// the key must be exactly 32 bytes
+    pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
+        let cipher = ChaCha20Poly1305::new_from_slice(key).map_err(|_| {
+            anyhow::anyhow!(
+                "ChaCha20-Poly1305 key must be exactly 32 bytes, got {}",
+                key.len()
+            )
+        })?;
+        Ok(ChaCha20Poly1305Cipher::new(cipher))
+    }
+}
+
+/// encrypt a secret key at rest: the magic, a random salt, then the secret sealed with
+/// AES-256-GCM under a key derived from the passphrase with Argon2id
+pub(super) fn protect_key(secret: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = passphrase_cipher(passphrase, &salt)?;
+    let mut protected = PROTECTED_KEY_MAGIC.to_vec();
+    protected.extend_from_slice(&salt);
+    protected.extend_from_slice(&seal(&cipher, &mut &secret[..])?);
+    Ok(protected)
+}
+
+/// decrypt a secret key from `protect_key`
+pub(super) fn unprotect_key(protected: &[u8], passphrase: &str) -> anyhow::Result<Vec<u8>> {
+    let data = protected
+        .strip_prefix(PROTECTED_KEY_MAGIC)
+        .context("Not a passphrase-protected key")?;
+    if data.len() < SALT_LEN {
+        anyhow::bail!("Protected key is too short");
+    }
+    let (salt, sealed) = data.split_at(SALT_LEN);
+    let cipher = passphrase_cipher(passphrase, salt)?;
+    open(&cipher, sealed).context("Wrong passphrase for the protected key")
+}
+
+fn passphrase_cipher(passphrase: &str, salt: &[u8]) -> anyhow::Result<Aes256Gcm> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive a key from the passphrase: {}", e))?;
+    Ok(Aes256Gcm::new(&key.into()))
+}
+
+/// encrypt with a fresh random nonce and prepend it to the ciphertext
+fn seal<C: Aead + AeadCore>(cipher: &C, reader: &mut dyn Read) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let nonce = C::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, buf.as_slice())
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend_from_slice(&ciphertext);
+    Ok(encrypted)
+}
+
+/// decrypt `encrypted` and make sure it gives back `plaintext`
+fn check_round_trip(
+    cipher: &dyn DataDecrypt,
+    plaintext: &[u8],
+    encrypted: &[u8],
+) -> anyhow::Result<()> {
+    let decrypted = cipher
+        .decrypt(encrypted)
+        .context("Self-check failed: the ciphertext doesn't decrypt")?;
+    if decrypted != plaintext {
+        anyhow::bail!("Self-check failed: the ciphertext decrypts to different data");
+    }
+    Ok(())
+}
+
+/// split off the nonce written by `seal` and decrypt the rest
+fn open<C: Aead + AeadCore>(cipher: &C, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if data.len() < NONCE_LEN {
+        anyhow::bail!("Ciphertext is too short");
+    }
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    cipher
+        .decrypt(nonce.into(), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Decryption failed: wrong key or tampered ciphertext"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aes256gcm_round_trip() -> anyhow::Result<()> {
+        let cipher = Aes256GcmCipher::load("fixtures/crypt.key")?;
+        let data = b"hello world";
+        let encrypted = cipher.encrypt(&mut &data[..])?;
+        assert_eq!(encrypted.len(), NONCE_LEN + data.len() + 16);
+        assert_eq!(cipher.decrypt(&encrypted)?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aes256gcm_tampered() -> anyhow::Result<()> {
+        let cipher = Aes256GcmCipher::load("fixtures/crypt.key")?;
+        let data = b"hello world";
+        let mut encrypted = cipher.encrypt(&mut &data[..])?;
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(cipher.decrypt(&encrypted).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_chacha20_round_trip() -> anyhow::Result<()> {
+        let cipher = ChaCha20Poly1305Cipher::load("fixtures/crypt.key")?;
+        let data = b"hello world";
+        let encrypted = cipher.encrypt(&mut &data[..])?;
+        assert_eq!(encrypted.len(), NONCE_LEN + data.len() + 16);
+        assert_eq!(cipher.decrypt(&encrypted)?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chacha20_tampered() -> anyhow::Result<()> {
+        let cipher = ChaCha20Poly1305Cipher::load("fixtures/crypt.key")?;
+        let data = b"hello world";
+        let mut encrypted = cipher.encrypt(&mut &data[..])?;
+        encrypted[NONCE_LEN] ^= 1;
+        assert!(cipher.decrypt(&encrypted).is_err());
+
+        let aes = Aes256GcmCipher::load("fixtures/crypt.key")?;
+        let encrypted = cipher.encrypt(&mut &data[..])?;
+        assert!(aes.decrypt(&encrypted).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_encrypt_decrypt() -> anyhow::Result<()> {
+        for algo in [CryptAlgo::Aes256Gcm, CryptAlgo::ChaCha20] {
+            let encrypted =
+                process_encrypt("fixtures/lines.txt", "fixtures/crypt.key", algo, false)?;
+            let path = std::env::temp_dir().join(format!("rcli-lines.{}.enc", algo));
+            fs::write(&path, encrypted)?;
+            let decrypted = process_decrypt(&path.to_string_lossy(), "fixtures/crypt.key", algo)?;
+            assert_eq!(decrypted, fs::read("fixtures/lines.txt")?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_protect_key_round_trip() -> anyhow::Result<()> {
+        let secret = fs::read("fixtures/ed25519.sk")?;
+        let protected = protect_key(&secret, "correct horse")?;
+        assert!(protected.starts_with(PROTECTED_KEY_MAGIC));
+        assert_eq!(unprotect_key(&protected, "correct horse")?, secret);
+        assert!(unprotect_key(&protected, "battery staple").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_ending_in_newline() -> anyhow::Result<()> {
+        let mut key = [7u8; KEY_LEN];
+        key[KEY_LEN - 1] = b'\n';
+        let path = std::env::temp_dir().join("rcli-crypt-newline.key");
+        fs::write(&path, key)?;
+        let aes = Aes256GcmCipher::load(&path)?;
+        let chacha = ChaCha20Poly1305Cipher::load(&path)?;
+        let data = b"hello world";
+        let encrypted = aes.encrypt(&mut &data[..])?;
+        assert_eq!(Aes256GcmCipher::try_new(&key)?.decrypt(&encrypted)?, data);
+        let encrypted = chacha.encrypt(&mut &data[..])?;
+        assert_eq!(
+            ChaCha20Poly1305Cipher::try_new(&key)?.decrypt(&encrypted)?,
+            data
+        );
+        assert!(Aes256GcmCipher::try_new(&key[..31]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypt_self_check() -> anyhow::Result<()> {
+        for algo in [CryptAlgo::Aes256Gcm, CryptAlgo::ChaCha20] {
+            let encrypted =
+                process_encrypt("fixtures/lines.txt", "fixtures/crypt.key", algo, true)?;
+            assert!(!encrypted.is_empty());
+        }
+
+        let cipher = Aes256GcmCipher::load("fixtures/crypt.key")?;
+        let data = b"hello world";
+        let mut encrypted = cipher.encrypt(&mut &data[..])?;
+        check_round_trip(&cipher, data, &encrypted)?;
+        assert!(check_round_trip(&cipher, b"hello there", &encrypted).is_err());
+        encrypted[NONCE_LEN] ^= 1;
+        let err = check_round_trip(&cipher, data, &encrypted).unwrap_err();
+        assert!(err.to_string().starts_with("Self-check failed"));
+        Ok(())
+    }
+}
@@ -1,11 +1,25 @@
-use std::fs;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt,
+    io::{Read, Write},
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
-use csv::Reader;
+use anyhow::Context;
+use csv::{Position, ReaderBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tracing::info;
 
-use crate::cli::OutputFormat;
+use crate::{
+    cli::{InputFormat, OutputFormat},
+    get_reader_decompressed, get_writer, write_atomic, Timings,
+};
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 struct Player {
@@ -18,19 +32,1419 @@ struct Player {
     kit: u8,
 }
 
-pub fn process_csv(input: &str, output: String, format: OutputFormat) -> anyhow::Result<()> {
-    let mut reader = Reader::from_path(input)?;
+/// row-level changes applied after parsing, before serializing
+#[derive(Debug, Default, Clone)]
+pub struct CsvTransform {
+    /// sort rows by this column, numerically when both cells are numbers
+    pub sort_by: Option<String>,
+    pub desc: bool,
+    /// drop rows seen before, comparing `dedup_by` columns or all of them if empty
+    pub dedup: bool,
+    pub dedup_by: Vec<String>,
+    /// keep only the input rows at these 1-based positions, before any other change
+    pub rows: Vec<RowRange>,
+    /// skip this many rows, then keep at most `limit` of the rest (0 keeps all)
+    pub offset: usize,
+    pub limit: usize,
+    /// merge inputs with different headers instead of refusing them, filling missing cells with null
+    pub union_columns: bool,
+    /// cells with exactly one of these values become null
+    pub na_values: Vec<String>,
+    /// cells holding a JSON object or array become that structured value
+    pub parse_json_cells: bool,
+    /// the byte separating csv input fields, `,` if unset
+    pub delimiter: Option<u8>,
+    /// split csv input lines at these column widths in characters instead of at delimiters
+    pub fixed_widths: Vec<usize>,
+    /// remove control characters such as newlines and tabs from every cell
+    pub strip_control: bool,
+    /// swap rows and columns on csv output: each header becomes the first cell of a row
+    /// holding that column's values, and no header row is written
+    pub transpose: bool,
+    /// accept csv rows with more or fewer fields than the header, reporting them in
+    /// `CsvStats::bad_rows`; rows that can't be parsed at all are skipped and reported
+    pub flexible: bool,
+}
+
+/// an inclusive range of 1-based row positions: `10-20`, a single row `25`, or `30-` for
+/// row 30 to the end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl RowRange {
+    fn contains(&self, row: usize) -> bool {
+        row >= self.start && self.end.is_none_or(|end| row <= end)
+    }
+}
+
+impl FromStr for RowRange {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let position = |s: &str| -> anyhow::Result<usize> {
+            match s.trim().parse() {
+                Ok(0) => anyhow::bail!("Row positions start at 1"),
+                Ok(n) => Ok(n),
+                Err(_) => anyhow::bail!("Invalid row position {:?}", s),
+            }
+        };
+        let range = match s.split_once('-') {
+            None => {
+                let row = position(s)?;
+                RowRange {
+                    start: row,
+                    end: Some(row),
+                }
+            }
+            Some((start, "")) => RowRange {
+                start: position(start)?,
+                end: None,
+            },
+            Some((start, end)) => RowRange {
+                start: position(start)?,
+                end: Some(position(end)?),
+            },
+        };
+        if range.end.is_some_and(|end| end < range.start) {
+            anyhow::bail!("Row range {} ends before it starts", s);
+        }
+        Ok(range)
+    }
+}
+
+/// a csv row `--flexible` let through or skipped
+#[derive(Debug, Serialize)]
+pub struct BadRow {
+    pub input: String,
+    pub line: u64,
+    pub reason: String,
+    pub raw: String,
+}
+
+impl BadRow {
+    /// recover the raw row from the buffer at the record's starting position
+    fn new(input: &str, buf: &[u8], pos: Option<&Position>, reason: String) -> Self {
+        let (line, raw) = match pos {
+            Some(pos) => {
+                let rest = buf.get(pos.byte() as usize..).unwrap_or_default();
+                let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+                let raw = String::from_utf8_lossy(&rest[..end]);
+                (pos.line(), raw.trim_end_matches('\r').to_string())
+            }
+            None => (0, String::new()),
+        };
+        Self {
+            input: input.to_string(),
+            line,
+            reason,
+            raw,
+        }
+    }
+}
+
+/// counts from one conversion, shown by `--stats`
+#[derive(Debug)]
+pub struct CsvStats {
+    pub rows_read: usize,
+    pub rows_written: usize,
+    pub columns: usize,
+    pub bad_rows: Vec<BadRow>,
+    pub elapsed: Duration,
+}
+
+/// convert one or more inputs with the same headers into a single output
+pub fn process_csv(
+    inputs: &[impl AsRef<str>],
+    from: InputFormat,
+    output: String,
+    format: OutputFormat,
+    transform: &CsvTransform,
+    timings: &mut Timings,
+) -> anyhow::Result<CsvStats> {
+    let start = Instant::now();
+    let (content, mut stats) = process_csv_to_string(inputs, from, format, transform, timings)?;
+    timings.time("write", || -> anyhow::Result<()> {
+        if output == "-" {
+            let mut writer = get_writer(&output)?;
+            writer.write_all(content.as_bytes())?;
+            writer.flush()?;
+            return Ok(());
+        }
+        write_atomic(&output, |writer| Ok(writer.write_all(content.as_bytes())?))
+            .with_context(|| format!("Failed to write {}", output))
+    })?;
+    stats.elapsed = start.elapsed();
+    Ok(stats)
+}
+
+/// convert like `process_csv`, but return the serialized output instead of writing it
+///
+/// ```
+/// use rcli::{process_csv_to_string, CsvTransform, InputFormat, OutputFormat, Timings};
+///
+/// let transform = CsvTransform {
+///     sort_by: Some("Kit Number".into()),
+///     limit: 1,
+///     ..Default::default()
+/// };
+/// let (json, stats) = process_csv_to_string(
+///     &["assets/juventus.csv"],
+///     InputFormat::Csv,
+///     OutputFormat::Json,
+///     &transform,
+///     &mut Timings::default(),
+/// )?;
+/// assert_eq!((stats.rows_read, stats.rows_written), (27, 1));
+/// assert!(json.contains("Wojciech Szczesny"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn process_csv_to_string(
+    inputs: &[impl AsRef<str>],
+    from: InputFormat,
+    format: OutputFormat,
+    transform: &CsvTransform,
+    timings: &mut Timings,
+) -> anyhow::Result<(String, CsvStats)> {
+    let start = Instant::now();
+    if transform.transpose && !matches!(format, OutputFormat::Csv) {
+        anyhow::bail!("--transpose only works with csv output, not {}", format);
+    }
+    let inputs = inputs.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    let mut bad_rows = Vec::new();
+    let (headers, rows_read, ret) = load_records(&inputs, from, transform, timings, &mut bad_rows)?;
+    let content = timings.time("serialize", || -> anyhow::Result<String> {
+        let content = match format {
+            OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
+            OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
+            OutputFormat::Csv if transform.transpose => to_transposed_csv(&headers, &ret)?,
+            OutputFormat::Csv => to_csv(&headers, &ret)?,
+        };
+        Ok(content)
+    })?;
+    let stats = CsvStats {
+        rows_read,
+        rows_written: ret.len(),
+        columns: headers.len(),
+        bad_rows,
+        elapsed: start.elapsed(),
+    };
+    Ok((content, stats))
+}
+
+/// summarize each column of the inputs instead of converting them: its inferred type,
+/// how many cells are empty, how many distinct values it has, and the range of numbers
+pub fn process_csv_describe(
+    inputs: &[impl AsRef<str>],
+    from: InputFormat,
+    transform: &CsvTransform,
+    timings: &mut Timings,
+) -> anyhow::Result<String> {
+    let inputs = inputs.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    let (headers, _, records) = load_records(&inputs, from, transform, timings, &mut Vec::new())?;
+    let columns = headers
+        .iter()
+        .map(|column| describe_column(column, &records))
+        .collect::<Vec<_>>();
+    let report = serde_json::json!({ "rows": records.len(), "columns": columns });
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+/// read, parse, merge and transform the inputs, returning the headers, the number of rows
+/// read and the rows left after the transform, adding the malformed rows `--flexible`
+/// allowed to `bad_rows`
+fn load_records(
+    inputs: &[&str],
+    from: InputFormat,
+    transform: &CsvTransform,
+    timings: &mut Timings,
+    bad_rows: &mut Vec<BadRow>,
+) -> anyhow::Result<(Vec<String>, usize, Vec<Value>)> {
+    let bufs = timings.time("read", || -> anyhow::Result<Vec<Vec<u8>>> {
+        let mut bufs = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let mut reader = get_reader_decompressed(input)
+                .with_context(|| format!("Failed to open {}", input))?;
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf)?;
+            bufs.push(buf);
+        }
+        Ok(bufs)
+    })?;
+    let (headers, rows_read, ret) = timings.time("process", || -> anyhow::Result<_> {
+        let (headers, records) = merge_inputs(inputs, &bufs, from, transform, bad_rows)?;
+        let rows_read = records.len();
+        let records = transform.apply(&headers, records)?;
+        Ok((headers, rows_read, records))
+    })?;
+    info!("Parsed {} records from {}", rows_read, inputs.join(", "));
+    Ok((headers, rows_read, ret))
+}
+
+#[derive(Debug, Serialize)]
+struct ColumnSummary<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    nulls: usize,
+    distinct: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Value>,
+}
+
+/// missing, null and empty cells count as nulls; the type is the narrowest of
+/// int, float, bool and string that fits every other cell
+fn describe_column<'a>(column: &'a str, records: &[Value]) -> ColumnSummary<'a> {
+    let cells = records
+        .iter()
+        .map(|record| cell(record, column))
+        .filter(|cell| !cell.is_empty())
+        .collect::<Vec<_>>();
+    let distinct = cells.iter().collect::<HashSet<_>>().len();
+    let ints = cells
+        .iter()
+        .map(|c| parse_int(c).ok_or(()))
+        .collect::<Result<Vec<_>, _>>();
+    let floats = cells
+        .iter()
+        .map(|c| c.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>();
+    let is_bool = |c: &String| c.eq_ignore_ascii_case("true") || c.eq_ignore_ascii_case("false");
+    let (kind, min, max) = match (ints, floats) {
+        _ if cells.is_empty() => ("string", None, None),
+        (Ok(ints), _) => (
+            "int",
+            ints.iter().min().and_then(|&n| int_value(n)),
+            ints.iter().max().and_then(|&n| int_value(n)),
+        ),
+        (_, Ok(floats)) => (
+            "float",
+            floats.iter().copied().reduce(f64::min).map(Value::from),
+            floats.iter().copied().reduce(f64::max).map(Value::from),
+        ),
+        _ if cells.iter().all(is_bool) => ("bool", None, None),
+        _ => ("string", None, None),
+    };
+    ColumnSummary {
+        name: column,
+        kind,
+        nulls: records.len() - cells.len(),
+        distinct,
+        min,
+        max,
+    }
+}
+
+impl fmt::Display for CsvStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.elapsed.as_secs_f64();
+        let rate = if secs > 0.0 {
+            self.rows_read as f64 / secs
+        } else {
+            0.0
+        };
+        write!(
+            f,
+            "read {} rows, wrote {} rows, {} columns in {:?} ({:.0} rows/s)",
+            self.rows_read, self.rows_written, self.columns, self.elapsed, rate
+        )
+    }
+}
+
+/// parse every input and concatenate the records, checking that the headers agree
+fn merge_inputs(
+    inputs: &[&str],
+    bufs: &[Vec<u8>],
+    from: InputFormat,
+    transform: &CsvTransform,
+    bad_rows: &mut Vec<BadRow>,
+) -> anyhow::Result<(Vec<String>, Vec<Value>)> {
+    let fixed_width = !transform.fixed_widths.is_empty();
+    if fixed_width && !matches!(from, InputFormat::Csv) {
+        anyhow::bail!("--fixed-widths only applies to csv input");
+    }
+    let mut headers: Option<Vec<String>> = None;
+    let mut ret = Vec::new();
+    for (input, buf) in inputs.iter().zip(bufs) {
+        let (current, records) = match from {
+            InputFormat::Csv if fixed_width => {
+                parse_fixed_width_records(input, buf, &transform.fixed_widths)?
+            }
+            InputFormat::Csv => parse_records(input, buf, transform, bad_rows)?,
+            InputFormat::Json => {
+                let value = serde_json::from_slice(buf)
+                    .with_context(|| format!("Failed to parse {} as JSON", input))?;
+                parse_value_records(input, value)?
+            }
+            InputFormat::Yaml => {
+                let value = serde_yaml::from_slice(buf)
+                    .with_context(|| format!("Failed to parse {} as YAML", input))?;
+                parse_value_records(input, value)?
+            }
+        };
+        match &mut headers {
+            None => headers = Some(current),
+            Some(headers) if transform.union_columns => {
+                for column in current {
+                    if !headers.contains(&column) {
+                        headers.push(column);
+                    }
+                }
+            }
+            Some(headers) if *headers != current => anyhow::bail!(
+                "Headers of {} ({}) don't match {} ({}), pass --union-columns to merge them",
+                input,
+                current.join(", "),
+                inputs[0],
+                headers.join(", ")
+            ),
+            Some(_) => {}
+        }
+        ret.extend(records);
+    }
+    let headers = headers.unwrap_or_default();
+    if transform.union_columns {
+        // a column missing from one input is null in its records, not absent
+        for record in ret.iter_mut().filter_map(Value::as_object_mut) {
+            for column in &headers {
+                record.entry(column.as_str()).or_insert(Value::Null);
+            }
+        }
+    }
+    Ok((headers, ret))
+}
+
+impl CsvTransform {
+    fn apply(&self, headers: &[String], mut records: Vec<Value>) -> anyhow::Result<Vec<Value>> {
+        if !self.rows.is_empty() {
+            let mut row = 0;
+            records.retain(|_| {
+                row += 1;
+                self.rows.iter().any(|range| range.contains(row))
+            });
+        }
+        if !self.na_values.is_empty() || self.parse_json_cells || self.strip_control {
+            for value in records.iter_mut().filter_map(Value::as_object_mut) {
+                for cell in value.values_mut() {
+                    let Value::String(s) = cell else {
+                        continue;
+                    };
+                    if self.strip_control {
+                        s.retain(|c| !c.is_control());
+                    }
+                    if self.na_values.contains(s) {
+                        *cell = Value::Null;
+                    } else if self.parse_json_cells && s.trim_start().starts_with(['{', '[']) {
+                        if let Ok(parsed) = serde_json::from_str(s) {
+                            *cell = parsed;
+                        }
+                    }
+                }
+            }
+        }
+        if self.dedup || !self.dedup_by.is_empty() {
+            let keys = if self.dedup_by.is_empty() {
+                headers
+            } else {
+                for column in &self.dedup_by {
+                    check_column(headers, column, "dedup by")?;
+                }
+                &self.dedup_by
+            };
+            let mut seen = HashSet::new();
+            records.retain(|record| {
+                let key = keys.iter().map(|k| cell(record, k)).collect::<Vec<_>>();
+                seen.insert(key)
+            });
+        }
+        if let Some(column) = &self.sort_by {
+            check_column(headers, column, "sort by")?;
+            records.sort_by(|a, b| {
+                let ordering = compare_cells(&cell(a, column), &cell(b, column));
+                if self.desc {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        records.drain(..self.offset.min(records.len()));
+        if self.limit > 0 {
+            records.truncate(self.limit);
+        }
+        Ok(records)
+    }
+}
+
+/// the text of a cell: strings as they are, a missing value or null as empty, the rest as JSON
+fn cell(record: &Value, column: &str) -> String {
+    match &record[column] {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// join two csv files on a key column: each left row is combined with every right row
+/// holding the same key, in left order; with `left_join` left rows without a match are
+/// kept with null right cells, otherwise they are dropped. Right columns whose name is
+/// also a left column get a `_right` suffix
+pub fn process_csv_join(
+    left: &str,
+    right: &str,
+    on: &str,
+    left_join: bool,
+) -> anyhow::Result<Vec<Value>> {
+    let (left_headers, left_rows) = read_csv(left)?;
+    let (right_headers, right_rows) = read_csv(right)?;
+    check_column(&left_headers, on, &format!("join {} on", left))?;
+    check_column(&right_headers, on, &format!("join {} on", right))?;
+    let right_columns = right_headers
+        .iter()
+        .filter(|column| *column != on)
+        .map(|column| {
+            let name = if left_headers.contains(column) {
+                format!("{}_right", column)
+            } else {
+                column.clone()
+            };
+            (column.as_str(), name)
+        })
+        .collect::<Vec<_>>();
+    let mut index = HashMap::<String, Vec<&Value>>::new();
+    for row in &right_rows {
+        index.entry(cell(row, on)).or_default().push(row);
+    }
+    let mut joined = Vec::with_capacity(left_rows.len());
+    for row in left_rows {
+        match index.get(&cell(&row, on)) {
+            Some(matches) => {
+                for matched in matches {
+                    let mut row = row.clone();
+                    for (column, name) in &right_columns {
+                        row[name.as_str()] = matched[*column].clone();
+                    }
+                    joined.push(row);
+                }
+            }
+            None if left_join => {
+                let mut row = row;
+                for (_, name) in &right_columns {
+                    row[name.as_str()] = Value::Null;
+                }
+                joined.push(row);
+            }
+            None => {}
+        }
+    }
+    Ok(joined)
+}
+
+fn read_csv(input: &str) -> anyhow::Result<(Vec<String>, Vec<Value>)> {
+    let mut reader =
+        get_reader_decompressed(input).with_context(|| format!("Failed to open {}", input))?;
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    parse_records(input, &buf, &CsvTransform::default(), &mut Vec::new())
+}
+
+fn check_column(headers: &[String], column: &str, purpose: &str) -> anyhow::Result<()> {
+    if !headers.iter().any(|h| h == column) {
+        anyhow::bail!(
+            "No column named {:?} to {}, the headers are: {}",
+            column,
+            purpose,
+            headers.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// an integer cell in the range of i64 or u64; kept apart from floats so IDs beyond
+/// 2^53 aren't rounded
+fn parse_int(cell: &str) -> Option<i128> {
+    let n = cell.parse::<i128>().ok()?;
+    (i64::MIN as i128..=u64::MAX as i128)
+        .contains(&n)
+        .then_some(n)
+}
+
+fn int_value(n: i128) -> Option<Value> {
+    i64::try_from(n)
+        .map(Value::from)
+        .or_else(|_| u64::try_from(n).map(Value::from))
+        .ok()
+}
+
+/// numbers sort numerically and before everything else, the rest lexicographically;
+/// integers compare exactly rather than as floats
+fn compare_cells(a: &str, b: &str) -> Ordering {
+    if let (Some(a), Some(b)) = (parse_int(a), parse_int(b)) {
+        return a.cmp(&b);
+    }
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// write the rows `--flexible` let through as JSON Lines, one object per row
+pub fn process_csv_error_report(path: &Path, bad_rows: &[BadRow]) -> anyhow::Result<()> {
+    let report = process_csv_error_report_to_string(bad_rows)?;
+    write_atomic(path, |writer| Ok(writer.write_all(report.as_bytes())?))
+        .with_context(|| format!("Failed to write error report {}", path.display()))
+}
+
+/// the JSON Lines `process_csv_error_report` would write, e.g. for `--dry-run`
+pub fn process_csv_error_report_to_string(bad_rows: &[BadRow]) -> anyhow::Result<String> {
+    let mut report = String::new();
+    for row in bad_rows {
+        report.push_str(&serde_json::to_string(row)?);
+        report.push('\n');
+    }
+    Ok(report)
+}
+
+fn parse_records(
+    input: &str,
+    buf: &[u8],
+    transform: &CsvTransform,
+    bad_rows: &mut Vec<BadRow>,
+) -> anyhow::Result<(Vec<String>, Vec<Value>)> {
+    let flexible = transform.flexible;
+    let mut reader = ReaderBuilder::new()
+        .delimiter(transform.delimiter.unwrap_or(b','))
+        .flexible(flexible)
+        .from_reader(buf);
     let mut ret = Vec::with_capacity(128);
-    let headers = reader.headers()?.clone();
-    for result in reader.records() {
-        let record = result?;
+    let headers = reader
+        .headers()
+        .with_context(|| format!("Failed to read headers of {}", input))?
+        .clone();
+    for (i, result) in reader.records().enumerate() {
+        if flexible {
+            match &result {
+                Ok(record) if record.len() != headers.len() => bad_rows.push(BadRow::new(
+                    input,
+                    buf,
+                    record.position(),
+                    format!("expected {} fields, found {}", headers.len(), record.len()),
+                )),
+                Ok(_) => {}
+                Err(e) => {
+                    bad_rows.push(BadRow::new(input, buf, e.position(), e.to_string()));
+                    continue;
+                }
+            }
+        }
+        let record = result.map_err(|e| {
+            let context = match e.position() {
+                Some(pos) => format!(
+                    "Failed to parse record {} (line {}) of {}: {}",
+                    i + 1,
+                    pos.line(),
+                    input,
+                    BadRow::new(input, buf, Some(pos), String::new()).raw
+                ),
+                None => format!("Failed to parse record {} of {}", i + 1, input),
+            };
+            anyhow::Error::new(e).context(context)
+        })?;
         let json_value = headers.iter().zip(record.iter()).collect::<Value>();
         ret.push(json_value);
     }
-    let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
+    let headers = headers.iter().map(String::from).collect();
+    Ok((headers, ret))
+}
+
+/// split every line into fields of the given widths, trimming the padding; the first
+/// line holds the headers and characters past the last width are ignored
+fn parse_fixed_width_records(
+    input: &str,
+    buf: &[u8],
+    widths: &[usize],
+) -> anyhow::Result<(Vec<String>, Vec<Value>)> {
+    if widths.contains(&0) {
+        anyhow::bail!("Fixed widths must be greater than 0");
+    }
+    let text = std::str::from_utf8(buf).with_context(|| format!("{} is not UTF-8", input))?;
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+    let headers = match lines.next() {
+        Some(line) => split_fixed_width(line, widths),
+        None => anyhow::bail!("{} has no header line", input),
     };
-    fs::write(output, content)?;
-    Ok(())
+    let records = lines
+        .map(|line| {
+            let fields = split_fixed_width(line, widths);
+            headers.iter().map(String::as_str).zip(fields).collect()
+        })
+        .collect();
+    Ok((headers, records))
+}
+
+fn split_fixed_width(line: &str, widths: &[usize]) -> Vec<String> {
+    let mut chars = line.chars();
+    widths
+        .iter()
+        .map(|&width| {
+            let field: String = chars.by_ref().take(width).collect();
+            field.trim().to_string()
+        })
+        .collect()
+}
+
+/// take the records from an array of objects parsed from JSON or YAML;
+/// the headers are the keys of all objects, first seen first
+fn parse_value_records(input: &str, value: Value) -> anyhow::Result<(Vec<String>, Vec<Value>)> {
+    let Value::Array(records) = value else {
+        anyhow::bail!("{} is not an array of objects", input);
+    };
+    let mut headers = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, record) in records.iter().enumerate() {
+        let Value::Object(fields) = record else {
+            anyhow::bail!("Item {} of {} is not an object", i + 1, input);
+        };
+        for key in fields.keys() {
+            if seen.insert(key.as_str()) {
+                headers.push(key.clone());
+            }
+        }
+    }
+    Ok((headers, records))
+}
+
+/// write the records as CSV under the given headers, leaving missing fields empty
+fn to_csv(headers: &[String], records: &[Value]) -> anyhow::Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(headers)?;
+    for record in records {
+        writer.write_record(headers.iter().map(|h| cell(record, h)))?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// one line per column: its header, then its value in every record
+fn to_transposed_csv(headers: &[String], records: &[Value]) -> anyhow::Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for header in headers {
+        let cells = records.iter().map(|record| cell(record, header));
+        writer.write_record(std::iter::once(header.clone()).chain(cells))?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_process_csv_error_has_record_number() {
+        let output = std::env::temp_dir().join("rcli-malformed.json");
+        let output = output.to_string_lossy().to_string();
+        let err = process_csv(
+            &["fixtures/malformed.csv"],
+            InputFormat::Csv,
+            output,
+            OutputFormat::Json,
+            &CsvTransform::default(),
+            &mut Timings::default(),
+        )
+        .unwrap_err();
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("record 2"), "{}", msg);
+        assert!(msg.contains("line 3"), "{}", msg);
+        assert!(msg.contains(": bob,25"), "{}", msg);
+    }
+
+    #[test]
+    fn test_process_csv() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-juventus.json");
+        process_csv(
+            &["assets/juventus.csv"],
+            InputFormat::Csv,
+            output.to_string_lossy().to_string(),
+            OutputFormat::Json,
+            &CsvTransform::default(),
+            &mut Timings::default(),
+        )?;
+        let content = fs::read_to_string(output)?;
+        assert!(content.contains("Wojciech Szczesny"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_gzip() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-juventus-gz.json");
+        let expected = std::env::temp_dir().join("rcli-juventus-plain.json");
+        for (input, output) in [
+            ("fixtures/juventus.csv.gz", &output),
+            ("assets/juventus.csv", &expected),
+        ] {
+            let output = output.to_string_lossy().to_string();
+            process_csv(
+                &[input],
+                InputFormat::Csv,
+                output,
+                OutputFormat::Json,
+                &CsvTransform::default(),
+                &mut Timings::default(),
+            )?;
+        }
+        assert_eq!(fs::read_to_string(output)?, fs::read_to_string(expected)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_timings() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-juventus-timing.yaml");
+        let mut timings = Timings::default();
+        process_csv(
+            &["assets/juventus.csv"],
+            InputFormat::Csv,
+            output.to_string_lossy().to_string(),
+            OutputFormat::Yaml,
+            &CsvTransform::default(),
+            &mut timings,
+        )?;
+        let report = timings.to_string();
+        let phases = report
+            .lines()
+            .map(|l| l.split_whitespace().next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(phases, ["read", "process", "serialize", "write"]);
+        Ok(())
+    }
+
+    fn sorted_column(transform: &CsvTransform, column: &str) -> anyhow::Result<Vec<String>> {
+        let buf = fs::read("assets/juventus.csv")?;
+        let (headers, records) = parse_records(
+            "juventus.csv",
+            &buf,
+            &CsvTransform::default(),
+            &mut Vec::new(),
+        )?;
+        let records = transform.apply(&headers, records)?;
+        Ok(records
+            .iter()
+            .map(|r| r[column].as_str().unwrap().to_string())
+            .collect())
+    }
+
+    #[test]
+    fn test_sort_by_numeric_asc() -> anyhow::Result<()> {
+        let transform = CsvTransform {
+            sort_by: Some("Kit Number".into()),
+            ..Default::default()
+        };
+        let kits = sorted_column(&transform, "Kit Number")?;
+        let numbers = kits
+            .iter()
+            .map(|k| k.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(numbers.windows(2).all(|w| w[0] <= w[1]), "{:?}", numbers);
+        assert_eq!(numbers[0], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_by_string_desc() -> anyhow::Result<()> {
+        let transform = CsvTransform {
+            sort_by: Some("Name".into()),
+            desc: true,
+            ..Default::default()
+        };
+        let names = sorted_column(&transform, "Name")?;
+        assert!(names.windows(2).all(|w| w[0] >= w[1]), "{:?}", names);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_by_missing_column() {
+        let transform = CsvTransform {
+            sort_by: Some("Age".into()),
+            ..Default::default()
+        };
+        let err = sorted_column(&transform, "Name").unwrap_err().to_string();
+        assert!(err.contains("\"Age\""), "{}", err);
+        assert!(err.contains("Name, Position, DOB"), "{}", err);
+    }
+
+    #[test]
+    fn test_dedup() -> anyhow::Result<()> {
+        let buf = b"name,age\nalice,30\nbob,25\nalice,30\nalice,31\nbob,25\n";
+        let dedup = |transform: CsvTransform| -> anyhow::Result<Vec<Value>> {
+            let (headers, records) =
+                parse_records("people.csv", buf, &CsvTransform::default(), &mut Vec::new())?;
+            transform.apply(&headers, records)
+        };
+        let all = dedup(CsvTransform::default())?;
+        assert_eq!(all.len(), 5);
+
+        let exact = dedup(CsvTransform {
+            dedup: true,
+            ..Default::default()
+        })?;
+        assert_eq!(exact.len(), 3);
+        assert_eq!(exact[2]["age"], "31");
+
+        let by_name = dedup(CsvTransform {
+            dedup_by: vec!["name".into()],
+            ..Default::default()
+        })?;
+        assert_eq!(by_name.len(), 2);
+        assert_eq!(by_name[0]["age"], "30");
+
+        let err = dedup(CsvTransform {
+            dedup_by: vec!["city".into()],
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("name, age"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit_offset() -> anyhow::Result<()> {
+        let names = |offset, limit| {
+            let transform = CsvTransform {
+                offset,
+                limit,
+                ..Default::default()
+            };
+            sorted_column(&transform, "Name")
+        };
+        let all = names(0, 0)?;
+        assert_eq!(all.len(), 27);
+        assert_eq!(names(0, 3)?, all[..3]);
+        assert_eq!(names(25, 0)?, all[25..]);
+        assert_eq!(names(10, 5)?, all[10..15]);
+        assert_eq!(names(25, 5)?, all[25..]);
+        assert!(names(100, 5)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_stats() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-juventus-stats.json");
+        let transform = CsvTransform {
+            limit: 10,
+            ..Default::default()
+        };
+        let stats = process_csv(
+            &["assets/juventus.csv"],
+            InputFormat::Csv,
+            output.to_string_lossy().to_string(),
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        let line = stats.to_string();
+        assert!(
+            line.starts_with("read 27 rows, wrote 10 rows, 5 columns in "),
+            "{}",
+            line
+        );
+        assert!(line.ends_with(" rows/s)"), "{}", line);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_json_to_csv() -> anyhow::Result<()> {
+        let input = std::env::temp_dir().join("rcli-people.json");
+        let output = std::env::temp_dir().join("rcli-people.csv");
+        fs::write(
+            &input,
+            r#"[
+                {"name": "alice", "age": 30, "city": "paris"},
+                {"name": "bob", "age": 25},
+                {"name": "carol", "age": 41, "city": "rome"}
+            ]"#,
+        )?;
+        process_csv(
+            &[input.to_string_lossy()],
+            InputFormat::Json,
+            output.to_string_lossy().to_string(),
+            OutputFormat::Csv,
+            &CsvTransform::default(),
+            &mut Timings::default(),
+        )?;
+        let content = fs::read_to_string(output)?;
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "name,age,city");
+        assert_eq!(lines[2], "bob,25,");
+        assert_eq!(lines.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_multiple_inputs() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-juventus-merged.json");
+        let output = output.to_string_lossy().to_string();
+        let inputs = ["assets/juventus.csv", "fixtures/juventus.csv.gz"];
+        let stats = process_csv(
+            &inputs,
+            InputFormat::Csv,
+            output.clone(),
+            OutputFormat::Json,
+            &CsvTransform::default(),
+            &mut Timings::default(),
+        )?;
+        assert_eq!(stats.rows_written, 54);
+
+        let other = std::env::temp_dir().join("rcli-other-headers.csv");
+        fs::write(&other, "Name,Club\nAlex Sandro,Juventus\n")?;
+        let inputs = ["assets/juventus.csv".into(), other.to_string_lossy()];
+        let convert = |union_columns| {
+            let transform = CsvTransform {
+                union_columns,
+                ..Default::default()
+            };
+            process_csv(
+                &inputs,
+                InputFormat::Csv,
+                output.clone(),
+                OutputFormat::Json,
+                &transform,
+                &mut Timings::default(),
+            )
+        };
+        let err = convert(false).unwrap_err().to_string();
+        assert!(err.contains("Name, Club"), "{}", err);
+        let stats = convert(true)?;
+        assert_eq!((stats.rows_written, stats.columns), (28, 6));
+        Ok(())
+    }
+
+    #[test]
+    fn test_union_columns_fill_nulls() -> anyhow::Result<()> {
+        let left = std::env::temp_dir().join("rcli-union-left.csv");
+        let right = std::env::temp_dir().join("rcli-union-right.csv");
+        fs::write(&left, "Name,Position\nAlex Sandro,Defender\n")?;
+        fs::write(&right, "Name,Club\nDybala,Juventus\n")?;
+        let output = std::env::temp_dir().join("rcli-union.json");
+        let inputs = [left.to_string_lossy(), right.to_string_lossy()];
+        let transform = CsvTransform {
+            union_columns: true,
+            ..Default::default()
+        };
+        process_csv(
+            &inputs,
+            InputFormat::Csv,
+            output.to_string_lossy().to_string(),
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        let records: Value = serde_json::from_str(&fs::read_to_string(&output)?)?;
+        assert_eq!(
+            records,
+            serde_json::json!([
+                {"Name": "Alex Sandro", "Position": "Defender", "Club": null},
+                {"Name": "Dybala", "Position": null, "Club": "Juventus"},
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_na_values() -> anyhow::Result<()> {
+        let transform = CsvTransform {
+            na_values: vec!["NA".into(), "N/A".into(), "null".into()],
+            ..Default::default()
+        };
+        let (content, _) = process_csv_to_string(
+            &["fixtures/na.csv"],
+            InputFormat::Csv,
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        let records: Value = serde_json::from_str(&content)?;
+        assert_eq!(records[0]["age"], "30");
+        assert_eq!(records[0]["city"], "paris");
+        assert!(records[1]["age"].is_null());
+        assert!(records[1]["city"].is_null());
+        assert_eq!(records[2]["age"], "41");
+        assert!(records[2]["city"].is_null());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_yaml_to_csv() -> anyhow::Result<()> {
+        let (content, stats) = process_csv_to_string(
+            &["fixtures/people.yaml"],
+            InputFormat::Yaml,
+            OutputFormat::Csv,
+            &CsvTransform::default(),
+            &mut Timings::default(),
+        )?;
+        assert_eq!(stats.rows_written, 3);
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "name,age,city",
+                "alice,30,paris",
+                "bob,25,",
+                "carol,41,rome"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_describe() -> anyhow::Result<()> {
+        let report = process_csv_describe(
+            &["assets/juventus.csv"],
+            InputFormat::Csv,
+            &CsvTransform::default(),
+            &mut Timings::default(),
+        )?;
+        let report: Value = serde_json::from_str(&report)?;
+        assert_eq!(report["rows"], 27);
+        let kit = &report["columns"][4];
+        assert_eq!(kit["name"], "Kit Number");
+        assert_eq!(kit["type"], "int");
+        assert_eq!((&kit["min"], &kit["max"]), (&1.into(), &77.into()));
+        assert_eq!(report["columns"][0]["type"], "string");
+        assert!(report["columns"][0].get("min").is_none());
+
+        let transform = CsvTransform {
+            na_values: vec!["NA".into(), "N/A".into(), "null".into()],
+            ..Default::default()
+        };
+        let report = process_csv_describe(
+            &["fixtures/na.csv"],
+            InputFormat::Csv,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        let report: Value = serde_json::from_str(&report)?;
+        let age = &report["columns"][1];
+        assert_eq!((&age["nulls"], &age["distinct"]), (&1.into(), &2.into()));
+        assert_eq!(report["columns"][2]["nulls"], 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_json_cells() -> anyhow::Result<()> {
+        let buf = br#"id,data
+1,"{""a"":1}"
+2,"[1, 2]"
+3,{oops
+4,42
+"#;
+        let (headers, records) =
+            parse_records("cells.csv", buf, &CsvTransform::default(), &mut Vec::new())?;
+        let transform = CsvTransform {
+            parse_json_cells: true,
+            ..Default::default()
+        };
+        let records = transform.apply(&headers, records)?;
+        assert_eq!(records[0]["data"], serde_json::json!({ "a": 1 }));
+        assert_eq!(records[0]["data"]["a"], 1);
+        assert_eq!(records[1]["data"], serde_json::json!([1, 2]));
+        assert_eq!(records[2]["data"], "{oops");
+        assert_eq!(records[3]["data"], "42");
+        Ok(())
+    }
+
+    #[test]
+    fn test_fixed_width_input() -> anyhow::Result<()> {
+        let transform = CsvTransform {
+            fixed_widths: vec![10, 20, 5],
+            ..Default::default()
+        };
+        let (json, stats) = process_csv_to_string(
+            &["fixtures/fixed.txt"],
+            InputFormat::Csv,
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        assert_eq!(stats.rows_read, 3);
+        let value: Value = serde_json::from_str(&json)?;
+        assert_eq!(value[1]["name"], "Bob Smith");
+        assert_eq!(value[1]["city"], "New York");
+        assert_eq!(value[1]["age"], "7");
+        assert_eq!(value[2]["age"], "41");
+
+        let transform = CsvTransform {
+            fixed_widths: vec![10, 0],
+            ..Default::default()
+        };
+        assert!(process_csv_to_string(
+            &["fixtures/fixed.txt"],
+            InputFormat::Csv,
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_keeps_header_order() -> anyhow::Result<()> {
+        let header = "Name,Position,DOB,Nationality,Kit Number";
+        for _ in 0..3 {
+            let (json, _) = process_csv_to_string(
+                &["assets/juventus.csv"],
+                InputFormat::Csv,
+                OutputFormat::Json,
+                &CsvTransform::default(),
+                &mut Timings::default(),
+            )?;
+            let value: Value = serde_json::from_str(&json)?;
+            let keys: Vec<_> = value[0].as_object().unwrap().keys().cloned().collect();
+            assert_eq!(keys.join(","), header);
+
+            let (yaml, _) = process_csv_to_string(
+                &["assets/juventus.csv"],
+                InputFormat::Csv,
+                OutputFormat::Yaml,
+                &CsvTransform::default(),
+                &mut Timings::default(),
+            )?;
+            assert!(yaml.starts_with("- Name: Wojciech Szczesny\n  Position: Goalkeeper\n"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_control_characters() -> anyhow::Result<()> {
+        let input = std::env::temp_dir().join("rcli-control.csv");
+        fs::write(&input, "name,note\nalice,\"line one\nline two\ttab\"\n")?;
+        let input = input.to_string_lossy();
+        let convert = |format, strip_control| {
+            let transform = CsvTransform {
+                strip_control,
+                ..Default::default()
+            };
+            process_csv_to_string(
+                &[&*input],
+                InputFormat::Csv,
+                format,
+                &transform,
+                &mut Timings::default(),
+            )
+            .map(|(content, _)| content)
+        };
+
+        let json = convert(OutputFormat::Json, false)?;
+        assert!(json.contains(r#""note": "line one\nline two\ttab""#));
+        let csv = convert(OutputFormat::Csv, false)?;
+        assert!(csv.contains("alice,\"line one\nline two\ttab\"\n"));
+
+        let json = convert(OutputFormat::Json, true)?;
+        assert!(json.contains(r#""note": "line oneline twotab""#));
+        let csv = convert(OutputFormat::Csv, true)?;
+        assert_eq!(csv.lines().nth(1), Some("alice,line oneline twotab"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_integers_stay_exact() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir();
+        let json_input = dir.join("rcli-ids.json");
+        fs::write(
+            &json_input,
+            r#"[{"id": 9007199254740992}, {"id": 9007199254740993}]"#,
+        )?;
+        let transform = CsvTransform {
+            sort_by: Some("id".into()),
+            desc: true,
+            ..Default::default()
+        };
+        let (json, _) = process_csv_to_string(
+            &[json_input.to_string_lossy()],
+            InputFormat::Json,
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        let value: Value = serde_json::from_str(&json)?;
+        assert_eq!(value[0]["id"].as_u64(), Some(9007199254740993));
+        assert!(json.contains("9007199254740993"));
+
+        let csv_input = dir.join("rcli-ids.csv");
+        fs::write(
+            &csv_input,
+            "id\n9007199254740993\n18446744073709551615\n-1\n",
+        )?;
+        let report = process_csv_describe(
+            &[csv_input.to_string_lossy()],
+            InputFormat::Csv,
+            &CsvTransform::default(),
+            &mut Timings::default(),
+        )?;
+        let report: Value = serde_json::from_str(&report)?;
+        let id = &report["columns"][0];
+        assert_eq!(id["type"], "int");
+        assert_eq!(id["min"].as_i64(), Some(-1));
+        assert_eq!(id["max"].as_u64(), Some(u64::MAX));
+        Ok(())
+    }
+
+    #[test]
+    fn test_transpose() -> anyhow::Result<()> {
+        let transform = CsvTransform {
+            transpose: true,
+            ..Default::default()
+        };
+        let convert = |format| {
+            process_csv_to_string(
+                &["fixtures/matrix.csv"],
+                InputFormat::Csv,
+                format,
+                &transform,
+                &mut Timings::default(),
+            )
+        };
+        let (content, stats) = convert(OutputFormat::Csv)?;
+        assert_eq!(content, "a,1,4\nb,2,5\nc,3,6\n");
+        assert_eq!(stats.rows_written, 2);
+        let err = convert(OutputFormat::Json).unwrap_err();
+        assert!(err.to_string().contains("only works with csv output"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_flexible_error_report() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli-ragged.json");
+        let transform = CsvTransform {
+            flexible: true,
+            ..Default::default()
+        };
+        let stats = process_csv(
+            &["fixtures/ragged.csv"],
+            InputFormat::Csv,
+            output.to_string_lossy().to_string(),
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        assert_eq!(stats.rows_written, 4);
+
+        let report = std::env::temp_dir().join("rcli-ragged-errors.jsonl");
+        process_csv_error_report(&report, &stats.bad_rows)?;
+        let entries = fs::read_to_string(&report)?
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<Value>, _>>()?;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["line"], 3);
+        assert_eq!(entries[0]["reason"], "expected 3 fields, found 2");
+        assert_eq!(entries[0]["raw"], "bob,25");
+        assert_eq!(entries[1]["line"], 5);
+        assert_eq!(entries[1]["raw"], "dave,52,oslo,extra");
+
+        let strict = CsvTransform::default();
+        assert!(process_csv(
+            &["fixtures/ragged.csv"],
+            InputFormat::Csv,
+            output.to_string_lossy().to_string(),
+            OutputFormat::Json,
+            &strict,
+            &mut Timings::default(),
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_rows() -> anyhow::Result<()> {
+        let names = |rows: &str| {
+            let transform = CsvTransform {
+                rows: rows
+                    .split(',')
+                    .map(str::parse)
+                    .collect::<anyhow::Result<_>>()?,
+                ..Default::default()
+            };
+            sorted_column(&transform, "Name")
+        };
+        let all = names("1-")?;
+        assert_eq!(all.len(), 27);
+        assert_eq!(names("10-20")?, all[9..20]);
+        assert_eq!(
+            names("3,1,27")?,
+            [&all[0], &all[2], &all[26]].map(String::clone)
+        );
+        assert_eq!(names("25-")?, all[24..]);
+        assert_eq!(names("2-3,25-,26")?.len(), 5);
+        assert!(names("30-")?.is_empty());
+
+        assert!("0-3".parse::<RowRange>().is_err());
+        assert!("5-3".parse::<RowRange>().is_err());
+        assert!("a".parse::<RowRange>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_join() -> anyhow::Result<()> {
+        let join = |left_join| {
+            process_csv_join(
+                "fixtures/join_left.csv",
+                "fixtures/join_right.csv",
+                "id",
+                left_join,
+            )
+        };
+        let inner = join(false)?;
+        assert_eq!(
+            serde_json::to_string(&inner)?,
+            r#"[{"id":"1","name":"alice","team":"red","team_right":"red","score":"10"},"#
+                .to_owned()
+                + r#"{"id":"1","name":"alice","team":"red","team_right":"red","score":"12"},"#
+                + r#"{"id":"3","name":"carol","team":"red","team_right":"blue","score":"7"}]"#
+        );
+
+        let left = join(true)?;
+        let ids = left
+            .iter()
+            .map(|r| r["id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["1", "1", "2", "3", "4"]);
+        assert_eq!(left[2]["score"], Value::Null);
+        assert_eq!(left[4]["team_right"], Value::Null);
+
+        let err = process_csv_join(
+            "fixtures/join_left.csv",
+            "fixtures/join_right.csv",
+            "name",
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("join_right.csv"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_tab_delimited() -> anyhow::Result<()> {
+        let transform = CsvTransform {
+            delimiter: Some(b'\t'),
+            ..Default::default()
+        };
+        let (content, _) = process_csv_to_string(
+            &["fixtures/people.tsv"],
+            InputFormat::Csv,
+            OutputFormat::Json,
+            &transform,
+            &mut Timings::default(),
+        )?;
+        let records: Value = serde_json::from_str(&content)?;
+        assert_eq!(
+            records,
+            serde_json::json!([{"name": "alice", "age": "30"}, {"name": "bob", "age": "25"}])
+        );
+        Ok(())
+    }
 }
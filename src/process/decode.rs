@@ -0,0 +1,68 @@
+use std::{fmt, io::Read};
+
+use crate::{get_reader, Base64Format, ByteCodec, Hex, Timings};
+
+/// the encoding `process_decode_auto` found the input to be in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetectedEncoding {
+    Hex,
+    Base64(Base64Format),
+}
+
+impl fmt::Display for DetectedEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectedEncoding::Hex => write!(f, "hex"),
+            DetectedEncoding::Base64(format) => write!(f, "base64 ({})", format),
+        }
+    }
+}
+
+/// decode the input as hex, standard base64 or URL-safe base64, whichever fits first
+pub fn process_decode_auto(
+    input: &str,
+    timings: &mut Timings,
+) -> anyhow::Result<(DetectedEncoding, Vec<u8>)> {
+    let buf = timings.time("read", || -> anyhow::Result<String> {
+        let mut reader = get_reader(input)?;
+        let mut buf = String::new();
+        reader.read_to_string(&mut buf)?;
+        Ok(buf)
+    })?;
+    timings.time("process", || decode_auto(&buf))
+}
+
+fn decode_auto(data: &str) -> anyhow::Result<(DetectedEncoding, Vec<u8>)> {
+    if let Ok(decoded) = Hex.decode(data) {
+        return Ok((DetectedEncoding::Hex, decoded));
+    }
+    for format in [Base64Format::Standard, Base64Format::UrlSafe] {
+        if let Ok(decoded) = format.decode(data) {
+            return Ok((DetectedEncoding::Base64(format), decoded));
+        }
+    }
+    anyhow::bail!("Input is neither hex nor standard or URL-safe base64")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_auto() -> anyhow::Result<()> {
+        let (encoding, decoded) = decode_auto("68656c6c6f\n")?;
+        assert_eq!(encoding, DetectedEncoding::Hex);
+        assert_eq!(decoded, b"hello");
+
+        let (encoding, decoded) = decode_auto("aGVsbG8gd29ybGQ=")?;
+        assert_eq!(encoding, DetectedEncoding::Base64(Base64Format::Standard));
+        assert_eq!(decoded, b"hello world");
+
+        let (encoding, decoded) = decode_auto("-_8")?;
+        assert_eq!(encoding, DetectedEncoding::Base64(Base64Format::UrlSafe));
+        assert_eq!(decoded, [0xfb, 0xff]);
+
+        assert!(decode_auto("not encoded!").is_err());
+        Ok(())
+    }
+}
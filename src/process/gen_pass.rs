@@ -1,10 +1,56 @@
-use rand::seq::SliceRandom; // Import the SliceRandom trait
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, RngCore, SeedableRng};
 
 const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
 const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
 const NUMBER: &[u8] = b"123456789";
 const SYMBOL: &[u8] = b"!@#$%^&*_";
+const AMBIGUOUS: &[u8] = b"0O1lI";
+const WORDLIST: &str = include_str!("../../assets/wordlist.txt");
 
+/// everything `process_genpass_config` needs to build a password from character classes
+#[derive(Debug, Clone)]
+pub struct GenPassConfig {
+    pub length: u8,
+    pub upper: bool,
+    pub lower: bool,
+    pub number: bool,
+    pub symbol: bool,
+    /// leave out characters that are easy to confuse, like 0 and O
+    pub no_ambiguous: bool,
+    /// at least this many characters from each class; an enabled class always gets one
+    pub min_upper: u8,
+    pub min_lower: u8,
+    pub min_number: u8,
+    pub min_symbol: u8,
+    /// never put the same character twice in a row
+    pub no_repeats: bool,
+    /// the symbol class, instead of the default `!@#$%^&*_`
+    pub symbols: Option<String>,
+    /// seed the RNG for reproducible output, for tests only
+    pub seed: Option<u64>,
+}
+
+impl Default for GenPassConfig {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            upper: true,
+            lower: true,
+            number: true,
+            symbol: true,
+            no_ambiguous: false,
+            min_upper: 0,
+            min_lower: 0,
+            min_number: 0,
+            min_symbol: 0,
+            no_repeats: false,
+            symbols: None,
+            seed: None,
+        }
+    }
+}
+
+/// a password from the enabled classes, with every other `GenPassConfig` option at its default
 pub fn process_genpass(
     length: u8,
     upper: bool,
@@ -12,24 +58,86 @@ pub fn process_genpass(
     number: bool,
     symbol: bool,
 ) -> anyhow::Result<String> {
-    let mut rng = rand::thread_rng();
-    let mut password = Vec::new();
-    let mut chars = Vec::new();
-    if upper {
-        chars.extend_from_slice(UPPER);
-        password.push(*UPPER.choose(&mut rng).expect("UPPER won't be empty"));
+    process_genpass_config(&GenPassConfig {
+        length,
+        upper,
+        lower,
+        number,
+        symbol,
+        ..Default::default()
+    })
+}
+
+/// draw a password from the enabled classes, meeting every per-class minimum
+pub fn process_genpass_config(config: &GenPassConfig) -> anyhow::Result<String> {
+    let GenPassConfig {
+        length,
+        upper,
+        lower,
+        number,
+        symbol,
+        no_ambiguous,
+        min_upper,
+        min_lower,
+        min_number,
+        min_symbol,
+        no_repeats,
+        ref symbols,
+        seed,
+    } = *config;
+    if length == 0 {
+        anyhow::bail!("Password length must be at least 1");
+    }
+    let symbols = symbol_set(symbols.as_deref())?;
+    let classes = [
+        ("uppercase", upper, min_upper, UPPER),
+        ("lowercase", lower, min_lower, LOWER),
+        ("number", number, min_number, NUMBER),
+        ("symbol", symbol, min_symbol, symbols.as_slice()),
+    ];
+    let mut required = 0;
+    for (name, enabled, min, _) in classes {
+        if !enabled && min > 0 {
+            anyhow::bail!(
+                "Cannot require {} {} characters when {} is disabled",
+                min,
+                name,
+                name
+            );
+        }
+        if enabled {
+            // every enabled class gets at least one character
+            required += min.max(1) as usize;
+        }
     }
-    if lower {
-        chars.extend_from_slice(LOWER);
-        password.push(*LOWER.choose(&mut rng).expect("LOWER won't be empty"));
+    if required == 0 {
+        anyhow::bail!("Enable at least one of uppercase, lowercase, number or symbol");
     }
-    if number {
-        chars.extend_from_slice(NUMBER);
-        password.push(*NUMBER.choose(&mut rng).expect("NUMBER won't be empty"));
+    if required > length as usize {
+        anyhow::bail!(
+            "Length {} is too short for the {} required characters",
+            length,
+            required
+        );
     }
-    if symbol {
-        chars.extend_from_slice(SYMBOL);
-        password.push(*SYMBOL.choose(&mut rng).expect("SYMBOL won't be empty"));
+
+    let mut rng = genpass_rng(seed);
+    // each character with the index of the class it was drawn from, since custom symbols
+    // may overlap the other classes
+    let mut password = Vec::new();
+    let mut chars = Vec::new();
+    let mut sets = Vec::new();
+    for (_, enabled, min, set) in classes {
+        if !enabled {
+            continue;
+        }
+        let set = candidates(set, no_ambiguous);
+        let class = sets.len();
+        chars.extend(set.iter().map(|&c| (c, class)));
+        for _ in 0..min.max(1) {
+            password.push((*set.choose(&mut rng).expect("class won't be empty"), class));
+        }
+        sets.push(set);
     }
     for _ in 0..(length - password.len() as u8) {
         let c = chars
@@ -38,7 +146,470 @@ pub fn process_genpass(
         password.push(*c);
     }
     password.shuffle(&mut rng);
-    let password = String::from_utf8(password)?;
+    if no_repeats {
+        // redraw within the same class so the per-class counts still hold
+        for i in 1..password.len() {
+            if password[i].0 != password[i - 1].0 {
+                continue;
+            }
+            let next = password.get(i + 1).map(|&(c, _)| c);
+            let choices = sets[password[i].1]
+                .iter()
+                .filter(|&&c| c != password[i - 1].0 && Some(c) != next)
+                .collect::<Vec<_>>();
+            password[i].0 = **choices
+                .choose(&mut rng)
+                .ok_or_else(|| anyhow::anyhow!("Not enough characters to avoid repeats"))?;
+        }
+    }
+    let password = String::from_utf8(password.into_iter().map(|(c, _)| c).collect())?;
 
     Ok(password)
 }
+
+/// the thread's secure RNG, or a reproducible one when a seed is given for testing
+fn genpass_rng(seed: Option<u64>) -> Box<dyn RngCore> {
+    match seed {
+        Some(seed) => Box::new(StdRng::seed_from_u64(seed)),
+        None => Box::new(rand::thread_rng()),
+    }
+}
+
+/// draw every character uniformly from the given charset, ignoring the classes
+pub fn process_genpass_charset(
+    length: u8,
+    charset: &str,
+    no_repeats: bool,
+) -> anyhow::Result<String> {
+    if length == 0 {
+        anyhow::bail!("Password length must be at least 1");
+    }
+    let chars = charset_chars(charset);
+    if chars.is_empty() {
+        anyhow::bail!("Charset must not be empty");
+    }
+    if no_repeats && chars.len() == 1 && length > 1 {
+        anyhow::bail!("A single-character charset can't avoid repeats");
+    }
+    let mut rng = rand::thread_rng();
+    let mut password = String::with_capacity(length as usize);
+    let mut prev = None;
+    for _ in 0..length {
+        let c = loop {
+            let c = *chars.choose(&mut rng).expect("chars won't be empty");
+            if !no_repeats || Some(c) != prev {
+                break c;
+            }
+        };
+        password.push(c);
+        prev = Some(c);
+    }
+
+    Ok(password)
+}
+
+/// join `words` words drawn from the embedded wordlist; that is the 2048-word BIP-39 English
+/// list, so each word adds 11 bits of entropy
+pub fn process_genpass_phrase(
+    words: u8,
+    separator: &str,
+    capitalize: bool,
+    trailing_number: bool,
+) -> anyhow::Result<String> {
+    if words == 0 {
+        anyhow::bail!("A passphrase needs at least one word");
+    }
+    let mut rng = rand::thread_rng();
+    let wordlist = parse_wordlist(WORDLIST);
+    let mut phrase = Vec::with_capacity(words as usize + 1);
+    for _ in 0..words {
+        let word = wordlist.choose(&mut rng).expect("WORDLIST won't be empty");
+        if capitalize {
+            let mut chars = word.chars();
+            let first = chars.next().expect("words won't be empty");
+            phrase.push(first.to_uppercase().chain(chars).collect::<String>());
+        } else {
+            phrase.push(word.to_string());
+        }
+    }
+    if trailing_number {
+        phrase.push(rng.gen_range(0..100).to_string());
+    }
+
+    Ok(phrase.join(separator))
+}
+
+/// theoretical entropy in bits of a password drawn from the enabled classes
+pub fn genpass_entropy(config: &GenPassConfig) -> f64 {
+    let symbols = symbol_set(config.symbols.as_deref()).unwrap_or_else(|_| SYMBOL.to_vec());
+    let size: usize = [
+        (config.upper, UPPER),
+        (config.lower, LOWER),
+        (config.number, NUMBER),
+        (config.symbol, symbols.as_slice()),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, set)| candidates(set, config.no_ambiguous).len())
+    .sum();
+    config.length as f64 * (size as f64).log2()
+}
+
+/// theoretical entropy in bits of a password drawn from a custom charset
+pub fn genpass_charset_entropy(length: u8, charset: &str) -> f64 {
+    length as f64 * (charset_chars(charset).len() as f64).log2()
+}
+
+/// theoretical entropy in bits of a passphrase from `process_genpass_phrase`, log2 of the
+/// wordlist size per word
+pub fn genpass_phrase_entropy(words: u8, trailing_number: bool) -> f64 {
+    let size = parse_wordlist(WORDLIST).len() as f64;
+    let number = if trailing_number { 100f64.log2() } else { 0.0 };
+    words as f64 * size.log2() + number
+}
+
+/// the words of a wordlist, one per line; the last field is the word, so a diceware list
+/// with its `11111<TAB>abacus` roll column works as well as a plain one
+fn parse_wordlist(list: &str) -> Vec<&str> {
+    list.lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .collect()
+}
+
+/// the distinct characters of a charset, in order of first appearance
+fn charset_chars(charset: &str) -> Vec<char> {
+    let mut chars = Vec::new();
+    for c in charset.chars() {
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    chars
+}
+
+/// the symbol class: the default set, or the distinct characters of `--symbols`
+fn symbol_set(symbols: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    let Some(symbols) = symbols else {
+        return Ok(SYMBOL.to_vec());
+    };
+    if symbols.is_empty() || !symbols.bytes().all(|c| c.is_ascii_graphic()) {
+        anyhow::bail!(
+            "Symbols must be printable ASCII characters, got {:?}",
+            symbols
+        );
+    }
+    let mut set = Vec::new();
+    for c in symbols.bytes() {
+        if !set.contains(&c) {
+            set.push(c);
+        }
+    }
+    Ok(set)
+}
+
+/// the characters of a class, without the lookalikes if asked to
+fn candidates(set: &[u8], no_ambiguous: bool) -> Vec<u8> {
+    set.iter()
+        .filter(|c| !no_ambiguous || !AMBIGUOUS.contains(c))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genpass_no_ambiguous() -> anyhow::Result<()> {
+        // every ambiguous character is a candidate symbol, so only the filter keeps them out
+        let mut config = GenPassConfig {
+            length: 32,
+            upper: false,
+            lower: false,
+            number: true,
+            min_symbol: 16,
+            symbols: Some("0O1lI!".into()),
+            ..Default::default()
+        };
+        let password = process_genpass_config(&config)?;
+        assert!(password.bytes().any(|c| AMBIGUOUS.contains(&c)));
+
+        config.no_ambiguous = true;
+        for _ in 0..1000 {
+            let password = process_genpass_config(&config)?;
+            assert!(!password.bytes().any(|c| AMBIGUOUS.contains(&c)));
+            assert!(password.bytes().filter(|&c| c == b'!').count() >= 16);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_no_ambiguous_keeps_every_class() -> anyhow::Result<()> {
+        for _ in 0..100 {
+            let password = process_genpass_config(&GenPassConfig {
+                length: 4,
+                no_ambiguous: true,
+                ..Default::default()
+            })?;
+            assert!(password.bytes().any(|c| c.is_ascii_uppercase()));
+            assert!(password.bytes().any(|c| c.is_ascii_lowercase()));
+            assert!(password.bytes().any(|c| c.is_ascii_digit()));
+            assert!(password.bytes().any(|c| SYMBOL.contains(&c)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_minimums() -> anyhow::Result<()> {
+        for _ in 0..1000 {
+            let password = process_genpass_config(&GenPassConfig {
+                length: 12,
+                min_upper: 1,
+                min_lower: 2,
+                min_number: 3,
+                min_symbol: 4,
+                ..Default::default()
+            })?;
+            let count = |set: &[u8]| password.bytes().filter(|c| set.contains(c)).count();
+            assert_eq!(password.len(), 12);
+            assert!(count(UPPER) >= 1);
+            assert!(count(LOWER) >= 2);
+            assert!(count(NUMBER) >= 3);
+            assert!(count(SYMBOL) >= 4);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_minimums_exceed_length() {
+        assert!(process_genpass_config(&GenPassConfig {
+            length: 8,
+            min_upper: 2,
+            min_lower: 2,
+            min_number: 2,
+            min_symbol: 3,
+            ..Default::default()
+        })
+        .is_err());
+        assert!(process_genpass_config(&GenPassConfig {
+            length: 8,
+            number: false,
+            min_number: 1,
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_genpass_length_too_short_for_classes() {
+        let err = process_genpass_config(&GenPassConfig {
+            length: 3,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("4 required characters"), "{}", err);
+        assert!(process_genpass_config(&GenPassConfig {
+            length: 4,
+            ..Default::default()
+        })
+        .is_ok());
+        assert!(process_genpass_config(&GenPassConfig {
+            length: 8,
+            upper: false,
+            lower: false,
+            number: false,
+            symbol: false,
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_genpass_zero_length() {
+        let err = process_genpass_config(&GenPassConfig {
+            length: 0,
+            lower: false,
+            number: false,
+            symbol: false,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("at least 1"), "{}", err);
+        assert!(process_genpass_charset(0, "abc", false).is_err());
+    }
+
+    #[test]
+    fn test_genpass_phrase() -> anyhow::Result<()> {
+        let phrase = process_genpass_phrase(5, "-", false, false)?;
+        let words = phrase.split('-').collect::<Vec<_>>();
+        assert_eq!(words.len(), 5);
+        assert!(words.iter().all(|w| parse_wordlist(WORDLIST).contains(w)));
+
+        let phrase = process_genpass_phrase(3, " ", true, true)?;
+        let words = phrase.split(' ').collect::<Vec<_>>();
+        assert_eq!(words.len(), 4);
+        assert!(words[..3].iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert!(words[3].parse::<u8>().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_custom_symbols() -> anyhow::Result<()> {
+        for _ in 0..100 {
+            let password = process_genpass_config(&GenPassConfig {
+                length: 32,
+                min_symbol: 8,
+                symbols: Some("!@#$".into()),
+                ..Default::default()
+            })?;
+            let symbols = password
+                .bytes()
+                .filter(|c| !c.is_ascii_alphanumeric())
+                .collect::<Vec<_>>();
+            assert!(symbols.len() >= 8);
+            assert!(symbols.iter().all(|c| b"!@#$".contains(c)), "{}", password);
+        }
+        let err = process_genpass_config(&GenPassConfig {
+            length: 8,
+            symbols: Some("".into()),
+            ..Default::default()
+        });
+        assert!(err.is_err());
+        let entropy = genpass_entropy(&GenPassConfig {
+            length: 1,
+            upper: false,
+            lower: false,
+            number: false,
+            symbols: Some("!@#$".into()),
+            ..Default::default()
+        });
+        assert_eq!(entropy, 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_overlapping_symbols_no_repeats() -> anyhow::Result<()> {
+        // `a` and `b` are both symbols and lowercase letters here; a repeated symbol must be
+        // redrawn from the symbols, or --min-symbol stops holding
+        let config = GenPassConfig {
+            length: 8,
+            upper: false,
+            number: false,
+            min_symbol: 6,
+            no_repeats: true,
+            symbols: Some("ab!".into()),
+            ..Default::default()
+        };
+        for _ in 0..1000 {
+            let password = process_genpass_config(&config)?;
+            let symbols = password.bytes().filter(|c| b"ab!".contains(c)).count();
+            assert!(symbols >= 6, "{}", password);
+            let password = password.as_bytes();
+            assert!((1..password.len()).all(|i| password[i] != password[i - 1]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_entropy() {
+        let entropy = genpass_entropy(&GenPassConfig::default());
+        let size = (UPPER.len() + LOWER.len() + NUMBER.len() + SYMBOL.len()) as f64;
+        assert_eq!(entropy, 16.0 * size.log2());
+        assert_eq!(genpass_phrase_entropy(4, false), 44.0);
+    }
+
+    #[test]
+    fn test_parse_wordlist() {
+        assert_eq!(parse_wordlist(WORDLIST).len(), 2048);
+        let eff = "11111\tabacus\n11112\tabdomen\n";
+        assert_eq!(parse_wordlist(eff), ["abacus", "abdomen"]);
+    }
+
+    #[test]
+    fn test_genpass_charset() -> anyhow::Result<()> {
+        let charset = "abc123!";
+        for _ in 0..100 {
+            let password = process_genpass_charset(20, charset, false)?;
+            assert_eq!(password.chars().count(), 20);
+            assert!(password.chars().all(|c| charset.contains(c)));
+        }
+        assert!(process_genpass_charset(20, "", false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_no_repeats() -> anyhow::Result<()> {
+        for _ in 0..1000 {
+            let password = process_genpass_config(&GenPassConfig {
+                length: 64,
+                symbol: false,
+                no_repeats: true,
+                ..Default::default()
+            })?;
+            let password = password.as_bytes();
+            assert!((1..password.len()).all(|i| password[i] != password[i - 1]));
+
+            let password = process_genpass_charset(64, "ab", true)?;
+            let password = password.as_bytes();
+            assert!((1..password.len()).all(|i| password[i] != password[i - 1]));
+        }
+        assert!(process_genpass_charset(2, "a", true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_is_reproducible() -> anyhow::Result<()> {
+        let generate = |seed| {
+            process_genpass_config(&GenPassConfig {
+                length: 20,
+                seed,
+                ..Default::default()
+            })
+        };
+        assert_eq!(generate(Some(42))?, generate(Some(42))?);
+        assert_ne!(generate(Some(42))?, generate(Some(43))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_positional_wrapper() -> anyhow::Result<()> {
+        for _ in 0..20 {
+            let password = process_genpass(20, true, false, true, false)?;
+            assert_eq!(password.len(), 20);
+            assert!(password
+                .bytes()
+                .all(|c| UPPER.contains(&c) || NUMBER.contains(&c)));
+        }
+        assert!(process_genpass(0, true, true, true, true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_config() -> anyhow::Result<()> {
+        let config = GenPassConfig {
+            length: 40,
+            symbol: false,
+            number: false,
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let password = process_genpass_config(&config)?;
+            assert_eq!(password.len(), 40);
+            assert!(password.chars().all(|c| c.is_ascii_alphabetic()));
+        }
+
+        let config = GenPassConfig {
+            length: 12,
+            upper: false,
+            lower: false,
+            symbols: Some("+-".into()),
+            ..Default::default()
+        };
+        let password = process_genpass_config(&config)?;
+        assert_eq!(password.len(), 12);
+        assert!(password
+            .chars()
+            .all(|c| "+-".contains(c) || NUMBER.contains(&(c as u8))));
+        Ok(())
+    }
+}
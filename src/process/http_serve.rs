@@ -1,77 +1,1349 @@
-use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use std::{
+    future::Future,
+    io::{SeekFrom, Write},
+    net::SocketAddr,
+    path::{Component, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, UNIX_EPOCH},
+};
 
+use anyhow::Context;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    handler::Handler,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    middleware::{self, Next},
+    response::Response,
     routing::get,
     Router,
 };
-use tower_http::services::fs::ServeDir;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncReadExt, AsyncSeekExt},
+    sync::Semaphore,
+};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{Any, CorsLayer},
+    services::fs::ServeDir,
+    trace::TraceLayer,
+};
 use tracing::{info, warn};
 
-#[derive(Debug)]
-struct HttpServeState {
-    path: PathBuf,
+use crate::UploadMode;
+
+/// how long in-flight requests may run after Ctrl-C before the server exits anyway
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct HttpServeConfig {
+    pub path: PathBuf,
+    pub addr: SocketAddr,
+    pub download: bool,
+    pub compression: bool,
+    pub access_log: bool,
+    /// the username and password every request must send via HTTP Basic auth
+    pub credentials: Option<(String, String)>,
+    /// accept `PUT /path` to write the request body under the served directory
+    pub allow_upload: bool,
+    /// largest upload body in bytes
+    pub max_upload_size: usize,
+    /// accept `POST /path` too, replacing the file or appending to it
+    pub upload_mode: Option<UploadMode>,
+    /// PEM certificate chain and private key to serve HTTPS instead of HTTP
+    pub tls: Option<(PathBuf, PathBuf)>,
+    /// answer `/` and requests for missing files with the root `index.html`
+    pub spa: bool,
+    /// add CORS headers, allowing any origin unless `cors_origins` lists some
+    pub cors: bool,
+    pub cors_origins: Vec<HeaderValue>,
+    /// answer every GET with this one file instead of serving `path`
+    pub file: Option<PathBuf>,
+    /// answer 503 instead of serving more than this many requests at once
+    pub max_connections: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FileQuery {
+    download: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    /// inclusive start and end offsets
+    Partial(usize, usize),
+    Unsatisfiable,
+}
+
+impl Default for HttpServeConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("."),
+            addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            download: false,
+            compression: true,
+            access_log: false,
+            credentials: None,
+            allow_upload: false,
+            max_upload_size: 10 * 1024 * 1024,
+            upload_mode: None,
+            tls: None,
+            spa: false,
+            cors: false,
+            cors_origins: Vec::new(),
+            file: None,
+            max_connections: None,
+        }
+    }
+}
+
+pub async fn process_http_serve(config: HttpServeConfig) -> anyhow::Result<()> {
+    if let Some(file) = &config.file {
+        if !file.is_file() {
+            anyhow::bail!("{} is not a file", file.display());
+        }
+    }
+    let served = config.file.as_ref().unwrap_or(&config.path);
+    info!("Serving {:?} on {}", served, config.addr);
+    match config.tls.clone() {
+        Some((cert, key)) => {
+            let tls = load_tls(&cert, &key).await?;
+            let listener = std::net::TcpListener::bind(config.addr)?;
+            serve_tls(listener, tls, router(config), shutdown_signal()).await?;
+        }
+        None => {
+            let listener = tokio::net::TcpListener::bind(config.addr).await?;
+            serve(listener, router(config), shutdown_signal()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// resolves on Ctrl-C, after telling the user open connections get `SHUTDOWN_GRACE` to finish
+async fn shutdown_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Failed to listen for Ctrl-C: {}", e);
+        return std::future::pending().await;
+    }
+    eprintln!(
+        "Shutting down, waiting up to {}s for open connections",
+        SHUTDOWN_GRACE.as_secs()
+    );
 }
 
-pub async fn process_http_serve(path: PathBuf, port: u16) -> anyhow::Result<()> {
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    info!("Serving {:?} on port {}", path, addr);
-    let state = HttpServeState { path: path.clone() };
+/// serve until `shutdown` resolves, then stop accepting and let in-flight requests finish
+/// for at most `SHUTDOWN_GRACE`
+async fn serve(
+    listener: tokio::net::TcpListener,
+    router: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let (tx, mut rx) = tokio::sync::watch::channel(false);
+    let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+        shutdown.await;
+        let _ = tx.send(true);
+    });
+    let deadline = async move {
+        if rx.wait_for(|&stopping| stopping).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(SHUTDOWN_GRACE).await;
+    };
+    tokio::select! {
+        res = server => res?,
+        _ = deadline => warn!("Connections still open after {}s, closing them", SHUTDOWN_GRACE.as_secs()),
+    }
+    Ok(())
+}
 
-    let router = Router::new()
-        .route("/*path", get(file_handler))
-        .nest_service("/tower", ServeDir::new(path))
-        .with_state(Arc::new(state));
+async fn load_tls(cert: &std::path::Path, key: &std::path::Path) -> anyhow::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to load TLS certificate {} and key {}",
+                cert.display(),
+                key.display()
+            )
+        })
+}
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, router).await?;
+async fn serve_tls(
+    listener: std::net::TcpListener,
+    tls: RustlsConfig,
+    router: Router,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let handle = Handle::new();
+    let stopper = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        stopper.graceful_shutdown(Some(SHUTDOWN_GRACE));
+    });
+    axum_server::from_tcp_rustls(listener, tls)
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await?;
     Ok(())
 }
 
+/// where `--access-log` lines go
+type AccessLog = Arc<Mutex<dyn Write + Send>>;
+
+fn router(config: HttpServeConfig) -> Router {
+    router_with_access_log(config, Arc::new(Mutex::new(std::io::stderr())))
+}
+
+fn router_with_access_log(config: HttpServeConfig, access_log: AccessLog) -> Router {
+    let state = Arc::new(config);
+    let mut router = if state.file.is_some() {
+        // the request path is ignored so nothing next to the file is reachable
+        Router::new()
+            .route("/", get(single_file_handler))
+            .route("/*path", get(single_file_handler))
+            .with_state(state.clone())
+    } else {
+        let mut files = get(file_handler);
+        let limit = DefaultBodyLimit::max(state.max_upload_size);
+        if state.allow_upload {
+            files = files.put(upload_handler.layer(limit));
+        }
+        if state.upload_mode.is_some() {
+            files = files.post(upload_handler.layer(limit));
+        }
+        Router::new()
+            .route("/", get(index_handler))
+            .route("/*path", files)
+            .nest_service("/tower", ServeDir::new(&state.path))
+            .with_state(state.clone())
+    };
+    if state.credentials.is_some() {
+        router = router.layer(middleware::from_fn_with_state(state.clone(), require_auth));
+    }
+    if state.compression {
+        // gzip/deflate/br/zstd, whichever the client's Accept-Encoding prefers
+        router = router.layer(CompressionLayer::new());
+    }
+    if state.cors {
+        let cors = if state.cors_origins.is_empty() {
+            CorsLayer::permissive()
+        } else {
+            CorsLayer::new()
+                .allow_origin(state.cors_origins.clone())
+                .allow_methods(Any)
+                .allow_headers(Any)
+        };
+        router = router.layer(cors);
+    }
+    if let Some(max) = state.max_connections {
+        let permits = Arc::new(Semaphore::new(max));
+        router = router.layer(middleware::from_fn_with_state(permits, limit_connections));
+    }
+    if state.access_log {
+        router = router.layer(middleware::from_fn_with_state(access_log, log_access));
+    }
+    // per-request spans and events at debug level, e.g. RUST_LOG=tower_http=debug
+    router.layer(TraceLayer::new_for_http())
+}
+
+async fn require_auth(
+    State(state): State<Arc<HttpServeConfig>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let authorized = match &state.credentials {
+        Some((username, password)) => basic_auth(req.headers())
+            .is_some_and(|(u, p)| check_credentials(&u, &p, username, password)),
+        None => true,
+    };
+    if authorized {
+        return next.run(req).await;
+    }
+    let mut res = Response::new("Unauthorized".into());
+    *res.status_mut() = StatusCode::UNAUTHORIZED;
+    res.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        HeaderValue::from_static("Basic realm=\"rcli\", charset=\"UTF-8\""),
+    );
+    res
+}
+
+/// the username and password of an `Authorization: Basic ...` header
+fn basic_auth(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, encoded) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (username, password) = decoded.split_once(':')?;
+    Some((username.to_string(), password.to_string()))
+}
+
+/// compare both fields in constant time so timing doesn't leak how much of the password matched
+fn check_credentials(
+    username: &str,
+    password: &str,
+    expected_user: &str,
+    expected_pass: &str,
+) -> bool {
+    let user = username.as_bytes().ct_eq(expected_user.as_bytes());
+    let pass = password.as_bytes().ct_eq(expected_pass.as_bytes());
+    (user & pass).into()
+}
+
+/// serve the request only while a permit is free, so a flood gets 503 instead of queueing
+async fn limit_connections(
+    State(permits): State<Arc<Semaphore>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Ok(_permit) = permits.try_acquire() else {
+        warn!("Too many concurrent requests, rejecting {}", req.uri());
+        let mut res = Response::new("Too many concurrent requests".into());
+        *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+        return res;
+    };
+    next.run(req).await
+}
+
+async fn log_access(State(log): State<AccessLog>, req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let uri = req.uri().clone();
+    let start = Instant::now();
+    let res = next.run(req).await;
+    let line = access_line(&method, &uri, res.status(), start.elapsed());
+    if let Ok(mut log) = log.lock() {
+        let _ = writeln!(log, "{}", line);
+    }
+    res
+}
+
+/// `GET /path 200 1.2ms`
+fn access_line(method: &Method, uri: &Uri, status: StatusCode, latency: Duration) -> String {
+    format!("{} {} {} {:?}", method, uri, status.as_u16(), latency)
+}
+
+async fn index_handler(
+    State(state): State<Arc<HttpServeConfig>>,
+    Query(query): Query<FileQuery>,
+    headers: HeaderMap,
+) -> (StatusCode, HeaderMap, Vec<u8>) {
+    serve_path(&state, "", query, &headers).await
+}
+
 async fn file_handler(
-    State(state): State<Arc<HttpServeState>>,
+    State(state): State<Arc<HttpServeConfig>>,
     Path(path): Path<String>,
-) -> (StatusCode, String) {
-    let p = std::path::Path::new(state.path.as_path()).join(path);
+    Query(query): Query<FileQuery>,
+    headers: HeaderMap,
+) -> (StatusCode, HeaderMap, Vec<u8>) {
+    serve_path(&state, &path, query, &headers).await
+}
+
+async fn single_file_handler(
+    State(state): State<Arc<HttpServeConfig>>,
+    Query(query): Query<FileQuery>,
+    headers: HeaderMap,
+) -> (StatusCode, HeaderMap, Vec<u8>) {
+    match &state.file {
+        Some(file) => serve_file(&state, file, query, &headers).await,
+        None => (StatusCode::NOT_FOUND, HeaderMap::new(), Vec::new()),
+    }
+}
+
+async fn serve_path(
+    state: &HttpServeConfig,
+    path: &str,
+    query: FileQuery,
+    req_headers: &HeaderMap,
+) -> (StatusCode, HeaderMap, Vec<u8>) {
+    let mut headers = HeaderMap::new();
+    let Some(p) = resolve(&state.path, path) else {
+        return (StatusCode::FORBIDDEN, headers, b"Forbidden".to_vec());
+    };
     info!("Reading file {:?}", p);
+    if state.spa && (!p.exists() || path.is_empty()) {
+        // the app's entry point answers `/` and every unknown route, and the client-side
+        // router takes it from there; other directories are still listed
+        let index = state.path.join("index.html");
+        return match tokio::fs::read(&index).await {
+            Ok(content) => {
+                headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/html; charset=utf-8"),
+                );
+                (StatusCode::OK, headers, content)
+            }
+            Err(e) => {
+                warn!("Error reading {:?}: {}", index, e);
+                (
+                    StatusCode::NOT_FOUND,
+                    headers,
+                    format!("File {} not found", p.display()).into_bytes(),
+                )
+            }
+        };
+    }
     if !p.exists() {
         (
             StatusCode::NOT_FOUND,
-            format!("File {} not found", p.display()),
+            headers,
+            format!("File {} not found", p.display()).into_bytes(),
         )
-    } else {
-        // if it is a directory list all files/subdirectories
-        // as <li><a href="/path/to/file">file</a></li>
-        // <html><body><ul>...</ul></body></html>
-        match tokio::fs::read_to_string(p).await {
-            Ok(content) => {
-                info!("Read {} bytes", content.len());
-                (StatusCode::OK, content)
+    } else if p.is_dir() {
+        match list_dir(&p, path).await {
+            Ok(listing) => {
+                headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/html; charset=utf-8"),
+                );
+                (StatusCode::OK, headers, listing.into_bytes())
             }
             Err(e) => {
-                warn!("Error reading file: {}", e);
+                warn!("Error reading directory: {}", e);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Error reading file: {}", e),
+                    headers,
+                    format!("Error reading directory: {}", e).into_bytes(),
                 )
             }
         }
+    } else {
+        serve_file(state, &p, query, req_headers).await
+    }
+}
+
+async fn serve_file(
+    state: &HttpServeConfig,
+    p: &std::path::Path,
+    query: FileQuery,
+    req_headers: &HeaderMap,
+) -> (StatusCode, HeaderMap, Vec<u8>) {
+    let mut headers = HeaderMap::new();
+    let meta = match tokio::fs::metadata(p).await {
+        Ok(meta) => meta,
+        Err(e) => return read_error(headers, e),
+    };
+    if let Some(etag) = etag(&meta) {
+        let not_modified = etag_matches(req_headers, &etag);
+        headers.insert(header::ETAG, etag);
+        if not_modified {
+            return (StatusCode::NOT_MODIFIED, headers, Vec::new());
+        }
+    }
+    let len = meta.len() as usize;
+    headers.insert(header::CONTENT_TYPE, content_type(p));
+    let download = matches!(query.download.as_deref(), Some("1" | "true"));
+    if state.download || download {
+        if let Some(value) = content_disposition(p) {
+            headers.insert(header::CONTENT_DISPOSITION, value);
+        }
+    }
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    match byte_range(req_headers, len) {
+        ByteRange::Full => match tokio::fs::read(p).await {
+            Ok(content) => {
+                info!("Read {} bytes", content.len());
+                (StatusCode::OK, headers, content)
+            }
+            Err(e) => read_error(headers, e),
+        },
+        ByteRange::Partial(start, end) => match read_span(p, start, end).await {
+            Ok(content) => {
+                info!("Read bytes {}-{} of {}", start, end, len);
+                let value = format!("bytes {}-{}/{}", start, end, len);
+                if let Ok(value) = HeaderValue::from_str(&value) {
+                    headers.insert(header::CONTENT_RANGE, value);
+                }
+                (StatusCode::PARTIAL_CONTENT, headers, content)
+            }
+            Err(e) => read_error(headers, e),
+        },
+        ByteRange::Unsatisfiable => {
+            let value = format!("bytes */{}", len);
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(header::CONTENT_RANGE, value);
+            }
+            (StatusCode::RANGE_NOT_SATISFIABLE, headers, Vec::new())
+        }
+    }
+}
+
+/// read only the inclusive byte span, so a range request on a large file doesn't load all of it
+async fn read_span(p: &std::path::Path, start: usize, end: usize) -> std::io::Result<Vec<u8>> {
+    let mut file = tokio::fs::File::open(p).await?;
+    file.seek(SeekFrom::Start(start as u64)).await?;
+    let mut content = Vec::with_capacity(end - start + 1);
+    file.take((end - start + 1) as u64)
+        .read_to_end(&mut content)
+        .await?;
+    Ok(content)
+}
+
+fn read_error(headers: HeaderMap, e: std::io::Error) -> (StatusCode, HeaderMap, Vec<u8>) {
+    warn!("Error reading file: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        headers,
+        format!("Error reading file: {}", e).into_bytes(),
+    )
+}
+
+async fn upload_handler(
+    State(state): State<Arc<HttpServeConfig>>,
+    method: Method,
+    Path(path): Path<String>,
+    body: Bytes,
+) -> (StatusCode, String) {
+    let Some(p) = resolve(&state.path, &path) else {
+        return (StatusCode::FORBIDDEN, "Forbidden".to_string());
+    };
+    if p.is_dir() {
+        return (StatusCode::CONFLICT, format!("{} is a directory", path));
+    }
+    let written = async {
+        if let Some(parent) = p.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // PUT always replaces the file; POST follows --upload-mode
+        if method == Method::POST && state.upload_mode == Some(UploadMode::Append) {
+            append_locked(p.clone(), body.clone()).await
+        } else {
+            tokio::fs::write(&p, &body).await
+        }
+    };
+    match written.await {
+        Ok(()) => {
+            info!("Wrote {} bytes to {:?}", body.len(), p);
+            (StatusCode::CREATED, format!("/{}", path))
+        }
+        Err(e) => {
+            warn!("Error writing file: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error writing file: {}", e),
+            )
+        }
+    }
+}
+
+/// append under an exclusive lock, so uploads from several clients to one file don't interleave
+async fn append_locked(p: PathBuf, body: Bytes) -> std::io::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&p)?;
+        file.lock()?;
+        file.write_all(&body)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// join a request path onto the root; only plain names may follow it, so `..`
+/// (or an absolute path) can't escape the served directory
+fn resolve(root: &std::path::Path, path: &str) -> Option<PathBuf> {
+    let escapes = std::path::Path::new(path)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)));
+    if escapes {
+        warn!("Rejecting path outside the served directory: {}", path);
+        return None;
+    }
+    Some(root.join(path))
+}
+
+/// `"<size>-<mtime in ns>"` in hex, which changes whenever the file is rewritten
+fn etag(meta: &std::fs::Metadata) -> Option<HeaderValue> {
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    HeaderValue::from_str(&format!("\"{:x}-{:x}\"", meta.len(), mtime.as_nanos())).ok()
+}
+
+/// whether `If-None-Match` lists the ETag (compared weakly) or is `*`
+fn etag_matches(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Some(value) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// the single range a `Range: bytes=...` header asks for; anything we don't
+/// understand (including multiple ranges) falls back to the full body
+fn byte_range(headers: &HeaderMap, len: usize) -> ByteRange {
+    let Some(range) = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().strip_prefix("bytes="))
+    else {
+        return ByteRange::Full;
+    };
+    let Some((start, end)) = range.split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = match (start.trim(), end.trim()) {
+        // `bytes=-500` is the last 500 bytes
+        ("", suffix) => match suffix.parse::<usize>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (len.saturating_sub(n), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<usize>() {
+            Ok(start) => (start, len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+    if start >= len {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(start, end)
+    }
+}
+
+/// an HTML page linking every entry of the directory, subdirectories marked with a trailing `/`
+async fn list_dir(p: &std::path::Path, path: &str) -> std::io::Result<String> {
+    let mut entries = Vec::new();
+    let mut dir = tokio::fs::read_dir(p).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        let mut name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type().await?.is_dir() {
+            name.push('/');
+        }
+        entries.push(name);
+    }
+    entries.sort();
+
+    let base = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| format!("/{}", encode_segment(segment)))
+        .collect::<String>();
+    let mut html = String::from("<html><body><ul>\n");
+    for name in entries {
+        let href = match name.strip_suffix('/') {
+            Some(dir) => format!("{}/{}/", base, encode_segment(dir)),
+            None => format!("{}/{}", base, encode_segment(&name)),
+        };
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape_html(&href),
+            escape_html(&name)
+        ));
+    }
+    html.push_str("</ul></body></html>\n");
+    Ok(html)
+}
+
+/// percent-encode everything in a path segment but the unreserved characters of RFC 3986
+fn encode_segment(segment: &str) -> String {
+    const RESERVED: &AsciiSet = &NON_ALPHANUMERIC
+        .remove(b'-')
+        .remove(b'.')
+        .remove(b'_')
+        .remove(b'~');
+    utf8_percent_encode(segment, RESERVED).to_string()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// the MIME type for the file's extension, `application/octet-stream` when unknown
+fn content_type(p: &std::path::Path) -> HeaderValue {
+    let mime = mime_guess::from_path(p).first_or_octet_stream();
+    HeaderValue::from_str(mime.as_ref())
+        .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream"))
+}
+
+/// `attachment; filename="..."` so browsers download the file instead of rendering it; names
+/// outside printable ASCII also get the RFC 6266 `filename*=UTF-8''...` form, with an ASCII fallback
+fn content_disposition(p: &std::path::Path) -> Option<HeaderValue> {
+    // attr-char of RFC 5987, everything else is percent-encoded
+    const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+        .remove(b'!')
+        .remove(b'#')
+        .remove(b'$')
+        .remove(b'&')
+        .remove(b'+')
+        .remove(b'-')
+        .remove(b'.')
+        .remove(b'^')
+        .remove(b'_')
+        .remove(b'`')
+        .remove(b'|')
+        .remove(b'~');
+    let name = p.file_name()?.to_string_lossy();
+    let plain = |c: char| c.is_ascii() && !c.is_ascii_control();
+    let fallback: String = name
+        .chars()
+        .map(|c| if plain(c) { c } else { '_' })
+        .collect();
+    let fallback = fallback.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut value = format!("attachment; filename=\"{}\"", fallback);
+    if !name.chars().all(plain) {
+        value.push_str("; filename*=UTF-8''");
+        value.push_str(&utf8_percent_encode(&name, ATTR_CHAR).to_string());
     }
+    HeaderValue::from_str(&value).ok()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use std::fs;
+    use tower::ServiceExt;
 
     #[tokio::test]
     async fn test_file_handler() {
-        let state = Arc::new(HttpServeState {
-            path: PathBuf::from("."),
+        let state = Arc::new(HttpServeConfig::default());
+        let path = Path("Cargo.toml".to_string());
+        let query = Query(FileQuery::default());
+        let (status, headers, content) =
+            file_handler(State(state), path, query, HeaderMap::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(String::from_utf8(content)
+            .unwrap()
+            .trim()
+            .starts_with("[package]"));
+        assert!(headers.get(header::CONTENT_DISPOSITION).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_file_handler_download() {
+        let state = Arc::new(HttpServeConfig {
+            download: true,
+            ..Default::default()
         });
         let path = Path("Cargo.toml".to_string());
-        let (status, content) = file_handler(State(state), path).await;
+        let query = Query(FileQuery::default());
+        let (status, headers, _) = file_handler(State(state), path, query, HeaderMap::new()).await;
         assert_eq!(status, StatusCode::OK);
-        assert!(content.trim().starts_with("[package]"));
+        assert_eq!(
+            headers[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"Cargo.toml\""
+        );
+    }
+
+    #[test]
+    fn test_content_disposition() {
+        let value = |name| content_disposition(std::path::Path::new(name)).unwrap();
+        assert_eq!(
+            value(r#"a "b" \c.txt"#),
+            r#"attachment; filename="a \"b\" \\c.txt""#
+        );
+        assert_eq!(
+            value("résumé \"final\".pdf"),
+            r#"attachment; filename="r_sum_ \"final\".pdf"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.pdf"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_handler_download_query() {
+        let state = Arc::new(HttpServeConfig::default());
+        let path = Path("fixtures/b64.txt".to_string());
+        let query = Query(FileQuery {
+            download: Some("1".to_string()),
+        });
+        let (_, headers, _) = file_handler(State(state), path, query, HeaderMap::new()).await;
+        assert_eq!(
+            headers[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"b64.txt\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_router_lists_root() -> anyhow::Result<()> {
+        let router = router(HttpServeConfig::default());
+        let req = Request::get("/").body(Body::empty())?;
+        let res = router.clone().oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        let body = String::from_utf8(body.to_vec())?;
+        assert!(body.contains(r#"<a href="/Cargo.toml">Cargo.toml</a>"#));
+        assert!(body.contains(r#"<a href="/fixtures/">fixtures/</a>"#));
+
+        let req = Request::get("/fixtures").body(Body::empty())?;
+        let res = router.oneshot(req).await?;
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        let body = String::from_utf8(body.to_vec())?;
+        assert!(body.contains(r#"<a href="/fixtures/b64.txt">b64.txt</a>"#));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_dir_percent_encodes_links() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join("rcli-odd-names");
+        let dir = root.join("my docs");
+        fs::create_dir_all(&dir)?;
+        for name in ["a b.txt", "#1?.txt", "100%.txt", "<x>&y.txt"] {
+            fs::write(dir.join(name), "content")?;
+        }
+        let router = router(HttpServeConfig {
+            path: root,
+            ..Default::default()
+        });
+        let req = Request::get("/my%20docs").body(Body::empty())?;
+        let res = router.clone().oneshot(req).await?;
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        let body = String::from_utf8(body.to_vec())?;
+        for link in [
+            r#"<a href="/my%20docs/a%20b.txt">a b.txt</a>"#,
+            r#"<a href="/my%20docs/%231%3F.txt">#1?.txt</a>"#,
+            r#"<a href="/my%20docs/100%25.txt">100%.txt</a>"#,
+            r#"<a href="/my%20docs/%3Cx%3E%26y.txt">&lt;x&gt;&amp;y.txt</a>"#,
+        ] {
+            assert!(body.contains(link), "{} not in {}", link, body);
+        }
+
+        let req = Request::get("/my%20docs/%231%3F.txt").body(Body::empty())?;
+        let res = router.oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_path_traversal_rejected() {
+        let state = Arc::new(HttpServeConfig {
+            path: PathBuf::from("fixtures"),
+            ..Default::default()
+        });
+        for path in ["../Cargo.toml", "b64.txt/../../Cargo.toml", "/etc/passwd"] {
+            let query = Query(FileQuery::default());
+            let (status, _, _) = file_handler(
+                State(state.clone()),
+                Path(path.to_string()),
+                query,
+                HeaderMap::new(),
+            )
+            .await;
+            assert_eq!(status, StatusCode::FORBIDDEN, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_request() -> anyhow::Result<()> {
+        let router = router(HttpServeConfig::default());
+        let req = Request::get("/Cargo.toml")
+            .header(header::RANGE, "bytes=0-99")
+            .body(Body::empty())?;
+        let res = router.clone().oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        let len = fs::metadata("Cargo.toml")?.len();
+        assert_eq!(
+            res.headers()[header::CONTENT_RANGE],
+            format!("bytes 0-99/{}", len).as_str()
+        );
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert_eq!(body.len(), 100);
+        assert_eq!(&body[..], &fs::read("Cargo.toml")?[..100]);
+
+        let req = Request::get("/Cargo.toml")
+            .header(header::RANGE, format!("bytes={}-", len))
+            .body(Body::empty())?;
+        let res = router.oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            res.headers()[header::CONTENT_RANGE],
+            format!("bytes */{}", len).as_str()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_byte_range() {
+        let range = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::RANGE, HeaderValue::from_str(value).unwrap());
+            byte_range(&headers, 1000)
+        };
+        assert_eq!(byte_range(&HeaderMap::new(), 1000), ByteRange::Full);
+        assert_eq!(range("bytes=0-99"), ByteRange::Partial(0, 99));
+        assert_eq!(range("bytes=900-"), ByteRange::Partial(900, 999));
+        assert_eq!(range("bytes=-100"), ByteRange::Partial(900, 999));
+        assert_eq!(range("bytes=990-2000"), ByteRange::Partial(990, 999));
+        assert_eq!(range("bytes=1000-1001"), ByteRange::Unsatisfiable);
+        assert_eq!(range("bytes=0-1,5-9"), ByteRange::Full);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_compression() -> anyhow::Result<()> {
+        for compression in [true, false] {
+            let router = router(HttpServeConfig {
+                compression,
+                ..Default::default()
+            });
+            let req = Request::get("/assets/juventus.csv")
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .body(Body::empty())?;
+            let res = router.oneshot(req).await?;
+            assert_eq!(res.status(), StatusCode::OK);
+            let encoding = res.headers().get(header::CONTENT_ENCODING);
+            if compression {
+                assert_eq!(encoding.unwrap(), "gzip");
+            } else {
+                assert!(encoding.is_none());
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_access_log() -> anyhow::Result<()> {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let config = HttpServeConfig {
+            access_log: true,
+            ..Default::default()
+        };
+        let router = router_with_access_log(config, log.clone());
+        for path in ["/fixtures/b64.txt?download=1", "/missing.txt"] {
+            let req = Request::get(path).body(Body::empty())?;
+            router.clone().oneshot(req).await?;
+        }
+        let log = String::from_utf8(log.lock().unwrap().clone())?;
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", log);
+        assert!(lines[0].starts_with("GET /fixtures/b64.txt?download=1 200 "));
+        assert!(lines[1].starts_with("GET /missing.txt 404 "));
+
+        let uri = "/fixtures/b64.txt?download=1".parse()?;
+        let line = access_line(
+            &Method::GET,
+            &uri,
+            StatusCode::NOT_FOUND,
+            Duration::from_millis(3),
+        );
+        assert_eq!(line, "GET /fixtures/b64.txt?download=1 404 3ms");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth() -> anyhow::Result<()> {
+        let router = router(HttpServeConfig {
+            credentials: Some(("alice".to_string(), "s3cret".to_string())),
+            ..Default::default()
+        });
+        let get = |auth: Option<&str>| {
+            let mut req = Request::get("/fixtures/b64.txt");
+            if let Some(auth) = auth {
+                req = req.header(
+                    header::AUTHORIZATION,
+                    format!("Basic {}", STANDARD.encode(auth)),
+                );
+            }
+            req.body(Body::empty())
+        };
+
+        let res = router.clone().oneshot(get(Some("alice:s3cret"))?).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let res = router.clone().oneshot(get(Some("alice:wrong"))?).await?;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(res.headers().contains_key(header::WWW_AUTHENTICATE));
+
+        let res = router.oneshot(get(None)?).await?;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(res.headers()[header::WWW_AUTHENTICATE]
+            .to_str()?
+            .starts_with("Basic"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upload() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join("rcli-upload");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)?;
+        let config = HttpServeConfig {
+            path: root.clone(),
+            allow_upload: true,
+            max_upload_size: 16,
+            ..Default::default()
+        };
+        let router = router(config.clone());
+
+        let req = Request::put("/notes/hello.txt").body(Body::from("hello world"))?;
+        let res = router.clone().oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let req = Request::get("/notes/hello.txt").body(Body::empty())?;
+        let res = router.clone().oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], b"hello world");
+
+        let req = Request::put("/big.txt").body(Body::from(vec![b'x'; 17]))?;
+        let res = router.oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!root.join("big.txt").exists());
+
+        let router = super::router(HttpServeConfig {
+            allow_upload: false,
+            ..config
+        });
+        let req = Request::put("/other.txt").body(Body::from("hi"))?;
+        let res = router.oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tls_handshake() -> anyhow::Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig, RootCertStore};
+
+        let tls = load_tls("fixtures/cert.pem".as_ref(), "fixtures/key.pem".as_ref()).await?;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(serve_tls(
+            listener,
+            tls,
+            router(HttpServeConfig::default()),
+            std::future::pending(),
+        ));
+
+        let mut roots = RootCertStore::empty();
+        let mut pem = std::io::BufReader::new(fs::File::open("fixtures/cert.pem")?);
+        for cert in rustls_pemfile::certs(&mut pem) {
+            roots.add(cert?)?;
+        }
+        let config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+        let stream = tokio::net::TcpStream::connect(addr).await?;
+        let mut stream = connector
+            .connect(ServerName::try_from("localhost")?, stream)
+            .await?;
+        stream
+            .write_all(
+                b"GET /fixtures/b64.txt HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            )
+            .await?;
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).await?;
+        let res = String::from_utf8(res)?;
+        assert!(res.starts_with("HTTP/1.1 200 OK"));
+        let expected = fs::read_to_string("fixtures/b64.txt")?;
+        assert!(res.contains(expected.lines().next().unwrap()));
+
+        assert!(
+            load_tls("fixtures/key.pem".as_ref(), "fixtures/cert.pem".as_ref())
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown() -> anyhow::Result<()> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            router(HttpServeConfig::default()),
+            async move {
+                let _ = stopped.await;
+            },
+        ));
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await?;
+        stream
+            .write_all(b"GET /Cargo.toml HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).await?;
+        assert!(res.starts_with(b"HTTP/1.1 200 OK"));
+
+        stop.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server).await???;
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_single_file() -> anyhow::Result<()> {
+        let config = HttpServeConfig {
+            file: Some(PathBuf::from("fixtures/b64.txt")),
+            ..Default::default()
+        };
+        let req = Request::get("/anything").body(Body::empty())?;
+        let res = router(config.clone()).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain");
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], &fs::read("fixtures/b64.txt")?[..]);
+
+        let req = Request::get("/Cargo.toml").body(Body::empty())?;
+        let res = router(config).oneshot(req).await?;
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], &fs::read("fixtures/b64.txt")?[..]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_etag_conditional_get() -> anyhow::Result<()> {
+        let config = HttpServeConfig::default();
+        let req = Request::get("/fixtures/b64.txt").body(Body::empty())?;
+        let res = router(config.clone()).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers()[header::ETAG].clone();
+
+        let req = Request::get("/fixtures/b64.txt")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .body(Body::empty())?;
+        let res = router(config.clone()).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers()[header::ETAG], etag);
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert!(body.is_empty());
+
+        let req = Request::get("/fixtures/b64.txt")
+            .header(header::IF_NONE_MATCH, "\"stale\"")
+            .body(Body::empty())?;
+        let res = router(config).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_connections() -> anyhow::Result<()> {
+        let permits = Arc::new(Semaphore::new(2));
+        let slow = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    "done"
+                }),
+            )
+            .layer(middleware::from_fn_with_state(permits, limit_connections));
+        let requests = (0..3)
+            .map(|_| {
+                let req = Request::get("/").body(Body::empty()).unwrap();
+                tokio::spawn(slow.clone().oneshot(req))
+            })
+            .collect::<Vec<_>>();
+        let mut statuses = Vec::new();
+        for request in requests {
+            statuses.push(request.await??.status());
+        }
+        let rejected = statuses
+            .iter()
+            .filter(|s| **s == StatusCode::SERVICE_UNAVAILABLE)
+            .count();
+        assert_eq!(rejected, 1, "{:?}", statuses);
+
+        let config = HttpServeConfig {
+            max_connections: Some(1),
+            ..Default::default()
+        };
+        let req = Request::get("/fixtures/b64.txt").body(Body::empty())?;
+        assert_eq!(router(config).oneshot(req).await?.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spa_fallback() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join("rcli-spa");
+        fs::create_dir_all(&root)?;
+        fs::write(root.join("index.html"), "<html>app</html>")?;
+        let config = HttpServeConfig {
+            path: root.clone(),
+            spa: true,
+            ..Default::default()
+        };
+
+        let req = Request::get("/some/client/route").body(Body::empty())?;
+        let res = router(config.clone()).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], b"<html>app</html>");
+
+        let req = Request::get("/").body(Body::empty())?;
+        let res = router(config.clone()).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert_eq!(&body[..], b"<html>app</html>");
+
+        // real subdirectories keep their listing
+        fs::create_dir_all(root.join("assets"))?;
+        fs::write(root.join("assets/app.js"), "")?;
+        let req = Request::get("/assets").body(Body::empty())?;
+        let res = router(config.clone()).oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = to_bytes(res.into_body(), usize::MAX).await?;
+        assert!(String::from_utf8(body.to_vec())?.contains("app.js"));
+
+        let req = Request::get("/some/client/route").body(Body::empty())?;
+        let res = router(HttpServeConfig {
+            spa: false,
+            ..config
+        })
+        .oneshot(req)
+        .await?;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_content_type() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join("rcli-mime");
+        fs::create_dir_all(&root)?;
+        let state = Arc::new(HttpServeConfig {
+            path: root.clone(),
+            ..Default::default()
+        });
+        for (name, expected) in [
+            ("index.html", "text/html"),
+            ("app.js", "text/javascript"),
+            ("logo.png", "image/png"),
+            ("data.unknownext", "application/octet-stream"),
+        ] {
+            fs::write(root.join(name), "content")?;
+            let query = Query(FileQuery::default());
+            let (status, headers, _) = file_handler(
+                State(state.clone()),
+                Path(name.to_string()),
+                query,
+                HeaderMap::new(),
+            )
+            .await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(headers[header::CONTENT_TYPE], expected, "{}", name);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cors() -> anyhow::Result<()> {
+        let get = || {
+            Request::get("/fixtures/b64.txt")
+                .header(header::ORIGIN, "https://example.com")
+                .body(Body::empty())
+        };
+
+        let res = router(HttpServeConfig {
+            cors: true,
+            ..Default::default()
+        })
+        .oneshot(get()?)
+        .await?;
+        assert_eq!(res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+        let res = router(HttpServeConfig {
+            cors: true,
+            cors_origins: vec![HeaderValue::from_static("https://example.com")],
+            ..Default::default()
+        })
+        .oneshot(get()?)
+        .await?;
+        assert_eq!(
+            res.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+
+        let res = router(HttpServeConfig::default()).oneshot(get()?).await?;
+        assert!(!res
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_span() -> anyhow::Result<()> {
+        let content = fs::read("fixtures/lines.txt")?;
+        let p = std::path::Path::new("fixtures/lines.txt");
+        assert_eq!(read_span(p, 0, 0).await?, content[..1]);
+        assert_eq!(read_span(p, 5, 12).await?, content[5..=12]);
+        let last = content.len() - 1;
+        assert_eq!(read_span(p, last, last).await?, content[last..]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upload_append() -> anyhow::Result<()> {
+        let root = std::env::temp_dir().join("rcli-upload-append");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)?;
+        let config = HttpServeConfig {
+            path: root.clone(),
+            upload_mode: Some(UploadMode::Append),
+            ..Default::default()
+        };
+        let router = router(config.clone());
+
+        for body in ["hello ", "world"] {
+            let req = Request::post("/logs/app.log").body(Body::from(body))?;
+            let res = router.clone().oneshot(req).await?;
+            assert_eq!(res.status(), StatusCode::CREATED);
+        }
+        assert_eq!(
+            fs::read_to_string(root.join("logs/app.log"))?,
+            "hello world"
+        );
+
+        // PUT still needs --allow-upload
+        let req = Request::put("/logs/app.log").body(Body::from("gone"))?;
+        let res = router.clone().oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+        let uploads = (0..16).map(|i| {
+            let router = router.clone();
+            let line = format!("{}\n", ((b'a' + i) as char).to_string().repeat(1000));
+            tokio::spawn(async move {
+                let req = Request::post("/shared.log").body(Body::from(line))?;
+                anyhow::Ok(router.oneshot(req).await?.status())
+            })
+        });
+        for upload in uploads.collect::<Vec<_>>() {
+            assert_eq!(upload.await??, StatusCode::CREATED);
+        }
+        let shared = fs::read_to_string(root.join("shared.log"))?;
+        let lines: Vec<_> = shared.lines().collect();
+        assert_eq!(lines.len(), 16);
+        for line in lines {
+            assert_eq!(line.len(), 1000);
+            assert!(line.bytes().all(|c| c == line.as_bytes()[0]));
+        }
+
+        let router = super::router(HttpServeConfig {
+            upload_mode: Some(UploadMode::Overwrite),
+            ..config
+        });
+        let req = Request::post("/logs/app.log").body(Body::from("replaced"))?;
+        let res = router.oneshot(req).await?;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        assert_eq!(fs::read_to_string(root.join("logs/app.log"))?, "replaced");
+        Ok(())
     }
 }
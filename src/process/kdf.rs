@@ -0,0 +1,51 @@
+use argon2::Argon2;
+use rand::{rngs::OsRng, RngCore};
+
+pub const KDF_KEY_LEN: usize = 32;
+const KDF_SALT_LEN: usize = 16;
+
+/// derive a 32-byte key from a password with Argon2id (the crate's default cost), using
+/// the given salt or a fresh random one; returns the salt and the key
+pub fn process_kdf(
+    password: &str,
+    salt: Option<&[u8]>,
+) -> anyhow::Result<(Vec<u8>, [u8; KDF_KEY_LEN])> {
+    let salt = match salt {
+        Some(salt) => salt.to_vec(),
+        None => {
+            let mut salt = vec![0u8; KDF_SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        }
+    };
+    let mut key = [0u8; KDF_KEY_LEN];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), &salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Failed to derive a key from the password: {}", e))?;
+    Ok((salt, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+    use super::*;
+
+    #[test]
+    fn test_kdf_known_answer() -> anyhow::Result<()> {
+        // openssl kdf -keylen 32 -kdfopt "pass:correct horse" -kdfopt salt:rcli-salt-0001
+        //   -kdfopt iter:2 -kdfopt memcost:19456 -kdfopt lanes:1 -binary ARGON2ID
+        let (salt, key) = process_kdf("correct horse", Some(b"rcli-salt-0001"))?;
+        assert_eq!(salt, b"rcli-salt-0001");
+        assert_eq!(
+            STANDARD.encode(key),
+            "SXo2fN06EIgqymlQf17KKoKLIJfngo74rRP/v8+za9A="
+        );
+
+        let (salt, generated) = process_kdf("correct horse", None)?;
+        assert_eq!(salt.len(), KDF_SALT_LEN);
+        assert_ne!(generated, key);
+        assert!(process_kdf("correct horse", Some(b"short")).is_err());
+        Ok(())
+    }
+}
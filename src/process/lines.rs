@@ -0,0 +1,69 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, BufReader},
+};
+
+use regex::Regex;
+
+use crate::get_reader_decompressed;
+
+/// read the input line by line and apply grep, unique, sort, reverse and number in that order
+pub fn process_lines(
+    input: &str,
+    unique: bool,
+    sort: bool,
+    reverse: bool,
+    number: bool,
+    grep: Option<&str>,
+) -> anyhow::Result<Vec<String>> {
+    let reader = BufReader::new(get_reader_decompressed(input)?);
+    let grep = grep.map(Regex::new).transpose()?;
+    let mut seen = HashSet::new();
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(re) = &grep {
+            if !re.is_match(&line) {
+                continue;
+            }
+        }
+        if unique && !seen.insert(line.clone()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    if sort {
+        lines.sort();
+    }
+    if reverse {
+        lines.reverse();
+    }
+    if number {
+        lines = lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| format!("{:>6}\t{}", i + 1, line))
+            .collect();
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_lines_unique() -> anyhow::Result<()> {
+        let lines = process_lines("fixtures/lines.txt", true, false, false, false, None)?;
+        assert_eq!(lines, ["banana", "apple", "cherry", "apricot"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_lines_grep() -> anyhow::Result<()> {
+        let lines = process_lines("fixtures/lines.txt", false, true, false, true, Some("^a"))?;
+        assert_eq!(lines, ["     1\tapple", "     2\tapple", "     3\tapricot"]);
+        Ok(())
+    }
+}
@@ -1,11 +1,42 @@
 mod b64;
+mod checksum;
+mod codec;
+mod crypt;
 mod csv_convert;
+mod decode;
 mod gen_pass;
 mod http_serve;
+mod kdf;
+mod lines;
+#[cfg(feature = "qr")]
+mod qr;
 mod text;
 
-pub use b64::{process_decode, process_encode};
-pub use csv_convert::process_csv;
-pub use gen_pass::process_genpass;
-pub use http_serve::process_http_serve;
-pub use text::{process_text_generate, process_text_sign, process_text_verify};
+pub use b64::{
+    process_decode, process_decode_text, process_encode, process_encode_files, process_encode_text,
+};
+pub use checksum::process_checksum;
+pub use codec::{ByteCodec, Hex, StandardBase64, UrlSafeBase64};
+pub use crypt::{process_decrypt, process_encrypt};
+pub use csv_convert::{
+    process_csv, process_csv_describe, process_csv_error_report,
+    process_csv_error_report_to_string, process_csv_join, process_csv_to_string, BadRow, CsvStats,
+    CsvTransform, RowRange,
+};
+pub use decode::{process_decode_auto, DetectedEncoding};
+pub use gen_pass::{
+    genpass_charset_entropy, genpass_entropy, genpass_phrase_entropy, process_genpass,
+    process_genpass_charset, process_genpass_config, process_genpass_phrase, GenPassConfig,
+};
+pub use http_serve::{process_http_serve, HttpServeConfig};
+pub use kdf::{process_kdf, KDF_KEY_LEN};
+pub use lines::process_lines;
+#[cfg(feature = "qr")]
+pub use qr::{process_qr_png, process_qr_render};
+pub use text::{
+    key_passphrase, process_text_generate, process_text_key_fingerprint, process_text_protect_key,
+    process_text_sign, process_text_sign_embedded, process_text_sign_manifest,
+    process_text_sign_reader, process_text_sign_timestamped, process_text_verify,
+    process_text_verify_embedded, process_text_verify_manifest, process_text_verify_reader,
+    process_text_verify_timestamped, SignOptions, TextKey,
+};
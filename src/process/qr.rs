@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use image::{ImageFormat, Luma};
+use qrcode::{render::unicode, QrCode};
+
+/// render the data as a QR code drawn with unicode blocks for the terminal
+pub fn process_qr_render(data: &str) -> anyhow::Result<String> {
+    let code = QrCode::new(data)?;
+    let qr = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+    Ok(qr)
+}
+
+/// write the data as a QR code PNG image to the given path
+pub fn process_qr_png(data: &str, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let code = QrCode::new(data)?;
+    let image = code.render::<Luma<u8>>().build();
+    image.save_with_format(path, ImageFormat::Png)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::Color;
+
+    #[test]
+    fn test_process_qr_png() -> anyhow::Result<()> {
+        let password = "Qd7#kP2m$xW9!tLz";
+        let path = std::env::temp_dir().join("rcli-qr.png");
+        process_qr_png(password, &path)?;
+        assert!(std::fs::metadata(&path)?.len() > 0);
+
+        // sample the center of every module and compare with the expected code
+        let image = image::open(&path)?.to_luma8();
+        let code = QrCode::new(password)?;
+        let width = code.width() as u32;
+        let quiet_zone = 4;
+        let scale = image.width() / (width + 2 * quiet_zone);
+        let decoded = (0..width * width)
+            .map(|i| {
+                let x = (quiet_zone + i % width) * scale + scale / 2;
+                let y = (quiet_zone + i / width) * scale + scale / 2;
+                if image.get_pixel(x, y)[0] < 128 {
+                    Color::Dark
+                } else {
+                    Color::Light
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(decoded, code.to_colors());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_qr_render() -> anyhow::Result<()> {
+        let qr = process_qr_render("hello")?;
+        assert!(!qr.is_empty());
+        Ok(())
+    }
+}
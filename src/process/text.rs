@@ -1,10 +1,37 @@
-use std::{fs, io::Read, path::Path};
+use std::{
+    fs,
+    io::Read,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
-use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey};
+use anyhow::Context;
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use ed25519_dalek::{
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    Signer, SigningKey, Verifier, VerifyingKey,
+};
 use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 
-use crate::{get_reader, process_genpass, TextSignFormat};
+use super::crypt::{protect_key, unprotect_key, PROTECTED_KEY_MAGIC};
+use crate::{
+    get_reader, process_genpass_config, GenPassConfig, KeyFormat, SigEncoding, TextSignFormat,
+};
+
+const BLAKE3_KEY_CONTEXT: &str = "rcli 2024-04-20 blake3 text signing key";
+const PEM_PREFIX: &[u8] = b"-----BEGIN ";
+const PASSPHRASE_ENV: &str = "RCLI_KEY_PASSPHRASE";
+const BLAKE3_KEY_LEN: usize = 32;
+const BLAKE3_DEFAULT_LEN: usize = 32;
+const BLAKE3_MIN_LEN: usize = 16;
+const BLAKE3_MAX_LEN: usize = 1024;
+/// how far ahead of our clock a signer's clock may run
+const MAX_CLOCK_SKEW: u64 = 60;
 
 pub trait TextSign {
     /// sign the data from the reader and return the signature
@@ -21,11 +48,71 @@ pub trait KeyLoader {
         Self: Sized;
 }
 
+pub trait KeyFingerprint {
+    /// a digest identifying the key without revealing it
+    fn fingerprint(&self) -> String;
+}
+
 pub trait KeyGenerator {
     fn generate() -> anyhow::Result<Vec<Vec<u8>>>;
 }
+/// where the key for sign/verify comes from
+#[derive(Debug, Clone, Copy)]
+pub enum TextKey<'a> {
+    /// a key file on disk
+    Path(&'a str),
+    /// base64-encoded key bytes passed directly
+    Literal(&'a str),
+    /// base64-encoded key bytes in an environment variable, from `--key env:VAR`
+    Env(&'a str),
+}
+
+/// how sign and verify treat the key and signature beyond the format itself
+#[derive(Debug, Clone, Copy)]
+pub struct SignOptions {
+    /// derive the Blake3 key from key material of any length instead of requiring 32 bytes
+    pub derive_key: bool,
+    /// length in bytes of a Blake3 MAC
+    pub blake3_len: usize,
+    /// how signatures are written out and read back
+    pub sig_encoding: SigEncoding,
+    /// how a key file is encoded
+    pub key_format: KeyFormat,
+}
+
+impl Default for SignOptions {
+    fn default() -> Self {
+        Self {
+            derive_key: false,
+            blake3_len: BLAKE3_DEFAULT_LEN,
+            sig_encoding: SigEncoding::Base64,
+            key_format: KeyFormat::Raw,
+        }
+    }
+}
+
+impl SigEncoding {
+    fn encode(self, bytes: impl AsRef<[u8]>) -> String {
+        match self {
+            SigEncoding::Base64 => URL_SAFE_NO_PAD.encode(bytes),
+            SigEncoding::Hex => hex::encode(bytes),
+        }
+    }
+
+    fn decode(self, encoded: &str) -> anyhow::Result<Vec<u8>> {
+        match self {
+            SigEncoding::Base64 => URL_SAFE_NO_PAD
+                .decode(encoded)
+                .context("Signature is not valid URL-safe base64"),
+            SigEncoding::Hex => hex::decode(encoded).context("Signature is not valid hex"),
+        }
+    }
+}
+
 pub struct Blake3 {
     key: [u8; 32],
+    /// MAC length in bytes
+    len: usize,
 }
 
 pub struct Ed25519Signer {
@@ -36,48 +123,306 @@ pub struct Ed25519Verifier {
     key: VerifyingKey,
 }
 
-pub fn process_text_sign(input: &str, key: &str, format: TextSignFormat) -> anyhow::Result<String> {
+pub struct Secp256k1Signer {
+    key: k256::ecdsa::SigningKey,
+}
+
+pub struct Secp256k1Verifier {
+    key: k256::ecdsa::VerifyingKey,
+}
+
+pub fn process_text_sign(
+    input: &str,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<String> {
     let mut reader = get_reader(input)?;
-    let signature = match format {
-        TextSignFormat::Blake3 => {
-            let signer = Blake3::load(key)?;
-            signer.sign(&mut reader)?
-        }
-        TextSignFormat::Ed25519 => {
-            let signer = Ed25519Signer::load(key)?;
-            signer.sign(&mut reader)?
-        }
-    };
-    let signed = URL_SAFE_NO_PAD.encode(signature);
+    process_text_sign_reader(&mut reader, key, format, opts)
+}
+
+/// sign whatever the reader yields, e.g. an in-memory message
+pub fn process_text_sign_reader(
+    reader: &mut dyn Read,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<String> {
+    let signature = sign(reader, key, format, opts)?;
+    let signed = opts.sig_encoding.encode(signature);
     Ok(signed)
 }
 
 pub fn process_text_verify(
     input: &str,
-    key: &str,
+    key: TextKey,
     format: TextSignFormat,
     sig: &str,
+    opts: SignOptions,
 ) -> anyhow::Result<bool> {
     let mut reader = get_reader(input)?;
-    let signature = URL_SAFE_NO_PAD.decode(sig.trim())?;
-    let verified = match format {
-        TextSignFormat::Blake3 => {
-            let verifier = Blake3::load(key)?;
-            verifier.verify(&mut reader, &signature)?
-        }
-        TextSignFormat::Ed25519 => {
-            let verifier = Ed25519Verifier::load(key)?;
-            verifier.verify(&mut reader, &signature)?
+    process_text_verify_reader(&mut reader, key, format, sig, opts)
+}
+
+/// verify a signature over whatever the reader yields
+pub fn process_text_verify_reader(
+    reader: &mut dyn Read,
+    key: TextKey,
+    format: TextSignFormat,
+    sig: &str,
+    opts: SignOptions,
+) -> anyhow::Result<bool> {
+    let signature = opts.sig_encoding.decode(sig.trim())?;
+    verify(reader, key, format, &signature, opts)
+}
+
+/// sign the input and return a single blob carrying both the message and the signature:
+/// a 4-byte big-endian message length, the message, then the signature
+pub fn process_text_sign_embedded(
+    input: &str,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<String> {
+    let mut reader = get_reader(input)?;
+    let mut message = Vec::new();
+    reader.read_to_end(&mut message)?;
+    let signature = sign(&mut message.as_slice(), key, format, opts)?;
+    let len = u32::try_from(message.len()).context("Message is too large to embed")?;
+    let mut blob = len.to_be_bytes().to_vec();
+    blob.extend_from_slice(&message);
+    blob.extend_from_slice(&signature);
+    Ok(opts.sig_encoding.encode(blob))
+}
+
+/// verify a blob from `process_text_sign_embedded`, returning the embedded message and the result
+pub fn process_text_verify_embedded(
+    input: &str,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<(Vec<u8>, bool)> {
+    let mut reader = get_reader(input)?;
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let blob = opts.sig_encoding.decode(buf.trim())?;
+    let (len, rest) = blob
+        .split_first_chunk::<4>()
+        .context("Embedded signature is too short")?;
+    let len = u32::from_be_bytes(*len) as usize;
+    if rest.len() < len {
+        anyhow::bail!("Embedded signature is truncated");
+    }
+    let (message, signature) = rest.split_at(len);
+    let verified = verify(&mut &message[..], key, format, signature, opts)?;
+    Ok((message.to_vec(), verified))
+}
+
+/// sign the current Unix time (8 big-endian bytes) followed by the input, and return
+/// the timestamp followed by the signature so verify can rebuild the signed payload
+pub fn process_text_sign_timestamped(
+    input: &str,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<String> {
+    let mut reader = get_reader(input)?;
+    let signed = sign_at(&mut reader, key, format, opts, unix_now())?;
+    Ok(opts.sig_encoding.encode(signed))
+}
+
+/// verify a signature from `process_text_sign_timestamped`, failing if it is older than `max_age`
+/// seconds or dated further in the future than clock skew explains
+pub fn process_text_verify_timestamped(
+    input: &str,
+    key: TextKey,
+    format: TextSignFormat,
+    sig: &str,
+    opts: SignOptions,
+    max_age: u64,
+) -> anyhow::Result<bool> {
+    let mut reader = get_reader(input)?;
+    let signed = opts.sig_encoding.decode(sig.trim())?;
+    let (timestamp, signature) = signed
+        .split_first_chunk::<8>()
+        .context("Timestamped signature is too short")?;
+    let timestamp = u64::from_be_bytes(*timestamp);
+    let mut payload = timestamp.to_be_bytes().to_vec();
+    reader.read_to_end(&mut payload)?;
+    if !verify(&mut payload.as_slice(), key, format, signature, opts)? {
+        return Ok(false);
+    }
+    let now = unix_now();
+    if timestamp > now + MAX_CLOCK_SKEW {
+        anyhow::bail!(
+            "Signature is dated {}s in the future, more than the allowed {}s of clock skew",
+            timestamp - now,
+            MAX_CLOCK_SKEW
+        );
+    }
+    let age = now.saturating_sub(timestamp);
+    if age > max_age {
+        anyhow::bail!(
+            "Signature is {}s old, more than the allowed {}s",
+            age,
+            max_age
+        );
+    }
+    Ok(true)
+}
+
+fn sign_at(
+    reader: &mut dyn Read,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+    timestamp: u64,
+) -> anyhow::Result<Vec<u8>> {
+    let mut payload = timestamp.to_be_bytes().to_vec();
+    reader.read_to_end(&mut payload)?;
+    let signature = sign(&mut payload.as_slice(), key, format, opts)?;
+    let mut signed = timestamp.to_be_bytes().to_vec();
+    signed.extend_from_slice(&signature);
+    Ok(signed)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// sign every input and return a `<signature>  <filename>` line per file, like `sha256sum`
+pub fn process_text_sign_manifest(
+    inputs: &[String],
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<String> {
+    let mut manifest = String::new();
+    for input in inputs {
+        let signed = process_text_sign(input, key, format, opts)?;
+        manifest.push_str(&format!("{}  {}\n", signed, input));
+    }
+    Ok(manifest)
+}
+
+/// verify every `<signature>  <filename>` line of a manifest, returning each filename and
+/// result; a file that can't be checked, e.g. because it's missing, gets the reason as its
+/// error and the rest are still verified
+pub fn process_text_verify_manifest(
+    manifest: &str,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<Vec<(String, Result<bool, String>)>> {
+    let mut reader = get_reader(manifest)?;
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    let mut results = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
         }
-    };
+        let (sig, input) = line
+            .split_once("  ")
+            .with_context(|| format!("Malformed manifest line {}: {}", i + 1, line))?;
+        let verified = process_text_verify(input, key, format, sig, opts)
+            .with_context(|| format!("Failed to verify {}", input))
+            .map_err(|e| format!("{:#}", e));
+        results.push((input.to_string(), verified));
+    }
+    Ok(results)
+}
 
-    Ok(verified)
+/// the fingerprint of the key verify would use: the shared key for Blake3, the public key for Ed25519
+pub fn process_text_key_fingerprint(
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<String> {
+    let fingerprint = match format {
+        TextSignFormat::Blake3 => Blake3::from_key(key, opts)?.fingerprint(),
+        TextSignFormat::Ed25519 => key
+            .load(opts.key_format, Ed25519Verifier::try_new)?
+            .fingerprint(),
+        TextSignFormat::Secp256k1 => key
+            .load(opts.key_format, Secp256k1Verifier::try_new)?
+            .fingerprint(),
+    };
+    Ok(fingerprint)
 }
 
 pub fn process_text_generate(format: TextSignFormat) -> anyhow::Result<Vec<Vec<u8>>> {
     match format {
         TextSignFormat::Blake3 => Blake3::generate(),
         TextSignFormat::Ed25519 => Ed25519Signer::generate(),
+        TextSignFormat::Secp256k1 => Secp256k1Signer::generate(),
+    }
+}
+
+/// the passphrase for protected keys, read from `RCLI_KEY_PASSPHRASE` so it never shows up on
+/// the command line
+pub fn key_passphrase() -> anyhow::Result<String> {
+    std::env::var(PASSPHRASE_ENV)
+        .with_context(|| format!("Set {} to the key passphrase", PASSPHRASE_ENV))
+}
+
+/// encrypt a generated Ed25519 secret key with a passphrase before it is written to disk
+pub fn process_text_protect_key(
+    format: TextSignFormat,
+    secret: &[u8],
+    passphrase: &str,
+) -> anyhow::Result<Vec<u8>> {
+    match format {
+        TextSignFormat::Ed25519 => protect_key(secret, passphrase),
+        _ => anyhow::bail!("Passphrase protection is only supported for ed25519 keys"),
+    }
+}
+
+fn sign(
+    reader: &mut dyn Read,
+    key: TextKey,
+    format: TextSignFormat,
+    opts: SignOptions,
+) -> anyhow::Result<Vec<u8>> {
+    match format {
+        TextSignFormat::Blake3 => {
+            let signer = Blake3::from_key(key, opts)?;
+            signer.sign(reader)
+        }
+        TextSignFormat::Ed25519 => {
+            let signer = key.load(opts.key_format, Ed25519Signer::try_new)?;
+            signer.sign(reader)
+        }
+        TextSignFormat::Secp256k1 => {
+            let signer = key.load(opts.key_format, Secp256k1Signer::try_new)?;
+            signer.sign(reader)
+        }
+    }
+}
+
+fn verify(
+    reader: &mut dyn Read,
+    key: TextKey,
+    format: TextSignFormat,
+    signature: &[u8],
+    opts: SignOptions,
+) -> anyhow::Result<bool> {
+    match format {
+        TextSignFormat::Blake3 => {
+            let verifier = Blake3::from_key(key, opts)?;
+            verifier.verify(reader, signature)
+        }
+        TextSignFormat::Ed25519 => {
+            let verifier = key.load(opts.key_format, Ed25519Verifier::try_new)?;
+            verifier.verify(reader, signature)
+        }
+        TextSignFormat::Secp256k1 => {
+            let verifier = key.load(opts.key_format, Secp256k1Verifier::try_new)?;
+            verifier.verify(reader, signature)
+        }
     }
 }
 
@@ -85,7 +430,8 @@ impl TextSign for Blake3 {
     fn sign(&self, reader: &mut dyn Read) -> anyhow::Result<Vec<u8>> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
-        Ok(blake3::keyed_hash(&self.key, &buf).as_bytes().to_vec())
+        // the first 32 bytes of the extendable output are the plain keyed hash
+        Ok(self.mac(&buf))
     }
 }
 
@@ -98,13 +444,24 @@ impl TextSign for Ed25519Signer {
     }
 }
 
+impl TextSign for Secp256k1Signer {
+    fn sign(&self, reader: &mut dyn Read) -> anyhow::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        // RFC 6979 deterministic ECDSA over SHA-256, serialized as the 64-byte r || s
+        let signature: k256::ecdsa::Signature = self.key.sign(&buf);
+        Ok(signature.to_bytes().to_vec())
+    }
+}
+
 impl TextVerify for Blake3 {
     fn verify(&self, mut reader: impl Read, sig: &[u8]) -> anyhow::Result<bool> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
-        let hash = blake3::keyed_hash(&self.key, &buf);
-        let hash = hash.as_bytes();
-        Ok(hash == sig)
+        if sig.len() != self.len {
+            return Ok(false);
+        }
+        Ok(self.mac(&buf).ct_eq(sig).into())
     }
 }
 
@@ -112,7 +469,21 @@ impl TextVerify for Ed25519Verifier {
     fn verify(&self, mut reader: impl Read, sig: &[u8]) -> anyhow::Result<bool> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
-        let signature = ed25519_dalek::Signature::from_bytes(sig.try_into()?);
+        let Ok(sig) = <[u8; 64]>::try_from(sig) else {
+            return Ok(false);
+        };
+        let signature = ed25519_dalek::Signature::from_bytes(&sig);
+        Ok(self.key.verify(&buf, &signature).is_ok())
+    }
+}
+
+impl TextVerify for Secp256k1Verifier {
+    fn verify(&self, mut reader: impl Read, sig: &[u8]) -> anyhow::Result<bool> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        let Ok(signature) = k256::ecdsa::Signature::from_slice(sig) else {
+            return Ok(false);
+        };
         Ok(self.key.verify(&buf, &signature).is_ok())
     }
 }
@@ -123,7 +494,7 @@ impl KeyLoader for Blake3 {
         Self: Sized,
     {
         let key = fs::read(path)?;
-        Self::try_new(&key)
+        Self::try_new(trim_key_file(&key, BLAKE3_KEY_LEN))
     }
 }
 
@@ -147,9 +518,50 @@ impl KeyLoader for Ed25519Verifier {
     }
 }
 
+impl KeyLoader for Secp256k1Signer {
+    fn load(path: impl AsRef<Path>) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let key = fs::read(path)?;
+        Self::try_new(&key)
+    }
+}
+
+impl KeyLoader for Secp256k1Verifier {
+    fn load(path: impl AsRef<Path>) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let key = fs::read(path)?;
+        Self::try_new(&key)
+    }
+}
+
+impl KeyFingerprint for Blake3 {
+    fn fingerprint(&self) -> String {
+        sha256_fingerprint(&self.key)
+    }
+}
+
+impl KeyFingerprint for Ed25519Verifier {
+    fn fingerprint(&self) -> String {
+        sha256_fingerprint(self.key.as_bytes())
+    }
+}
+
+impl KeyFingerprint for Secp256k1Verifier {
+    fn fingerprint(&self) -> String {
+        sha256_fingerprint(&self.key.to_sec1_bytes())
+    }
+}
+
 impl KeyGenerator for Blake3 {
     fn generate() -> anyhow::Result<Vec<Vec<u8>>> {
-        let key = process_genpass(32, true, true, true, true)?;
+        let key = process_genpass_config(&GenPassConfig {
+            length: 32,
+            ..Default::default()
+        })?;
         let key = key.as_bytes().to_vec();
         Ok(vec![key])
     }
@@ -165,40 +577,220 @@ impl KeyGenerator for Ed25519Signer {
     }
 }
 
+impl KeyGenerator for Secp256k1Signer {
+    fn generate() -> anyhow::Result<Vec<Vec<u8>>> {
+        let sk = k256::ecdsa::SigningKey::random(&mut OsRng);
+        let pk = sk.verifying_key().to_sec1_bytes().to_vec();
+        let sk = sk.to_bytes().to_vec();
+        Ok(vec![sk, pk])
+    }
+}
+
 impl Blake3 {
     pub fn new(key: [u8; 32]) -> Self {
-        Self { key }
+        Self {
+            key,
+            len: BLAKE3_DEFAULT_LEN,
+        }
     }
+    /// the key must be exactly 32 bytes
     pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
-        let key = &key[..32];
-        let key = key.try_into()?;
+        let key = key.try_into().map_err(|_| {
+            anyhow::anyhow!(
+                "Blake3 key must be exactly 32 bytes, got {}; use --derive-key for other lengths",
+                key.len()
+            )
+        })?;
         let signer = Blake3::new(key);
         Ok(signer)
     }
+    /// derive a 32-byte key from key material of any length with `blake3::derive_key`
+    pub fn derive(key: &[u8]) -> Self {
+        Blake3::new(blake3::derive_key(BLAKE3_KEY_CONTEXT, key))
+    }
+    /// produce MACs of `len` bytes from the extendable output instead of the default 32
+    pub fn with_len(self, len: usize) -> anyhow::Result<Self> {
+        if !(BLAKE3_MIN_LEN..=BLAKE3_MAX_LEN).contains(&len) {
+            anyhow::bail!(
+                "Blake3 MAC length must be between {} and {} bytes, got {}",
+                BLAKE3_MIN_LEN,
+                BLAKE3_MAX_LEN,
+                len
+            );
+        }
+        Ok(Self { len, ..self })
+    }
+    fn from_key(key: TextKey, opts: SignOptions) -> anyhow::Result<Self> {
+        let blake3 = if opts.derive_key {
+            let material = key.read(opts.key_format)?;
+            // only a raw key file can carry a newline left by an editor; decoded bytes are exact
+            match (key, opts.key_format) {
+                (TextKey::Path(_), KeyFormat::Raw) => Self::derive(trim_newline(&material)),
+                _ => Self::derive(&material),
+            }
+        } else {
+            key.load(opts.key_format, Self::try_new)?
+        };
+        blake3.with_len(opts.blake3_len)
+    }
+    fn mac(&self, buf: &[u8]) -> Vec<u8> {
+        let mut mac = vec![0u8; self.len];
+        blake3::Hasher::new_keyed(&self.key)
+            .update(buf)
+            .finalize_xof()
+            .fill(&mut mac);
+        mac
+    }
+}
+
+fn decode_key(encoded: &str) -> Result<Vec<u8>, base64::DecodeError> {
+    let encoded = encoded.trim();
+    STANDARD
+        .decode(encoded)
+        .or_else(|_| URL_SAFE_NO_PAD.decode(encoded))
+}
+
+fn sha256_fingerprint(key: &[u8]) -> String {
+    format!("SHA256:{}", URL_SAFE_NO_PAD.encode(Sha256::digest(key)))
+}
+
+fn trim_newline(key: &[u8]) -> &[u8] {
+    let key = key.strip_suffix(b"\n").unwrap_or(key);
+    key.strip_suffix(b"\r").unwrap_or(key)
+}
+
+/// drop the newline an editor leaves after a raw key of `len` bytes; a file of any other
+/// length is returned untouched, so a key whose last byte happens to be 0x0a stays intact
+pub(super) fn trim_key_file(content: &[u8], len: usize) -> &[u8] {
+    let trimmed = trim_newline(content);
+    if content.len() > len && trimmed.len() == len {
+        trimmed
+    } else {
+        content
+    }
+}
+
+impl<'a> TextKey<'a> {
+    /// exactly one of the key file and the key literal must be given
+    pub fn try_new(path: Option<&'a str>, literal: Option<&'a str>) -> anyhow::Result<Self> {
+        match (path, literal) {
+            (Some(path), None) => match path.strip_prefix("env:") {
+                Some(var) => Ok(TextKey::Env(var)),
+                None => Ok(TextKey::Path(path)),
+            },
+            (None, Some(literal)) => Ok(TextKey::Literal(literal)),
+            (Some(_), Some(_)) => anyhow::bail!("Pass either --key or --key-literal, not both"),
+            (None, None) => anyhow::bail!("One of --key or --key-literal is required"),
+        }
+    }
+    /// load a raw key file through `KeyLoader`, or build the key from the decoded bytes
+    fn load<T: KeyLoader>(
+        &self,
+        format: KeyFormat,
+        from_bytes: impl FnOnce(&[u8]) -> anyhow::Result<T>,
+    ) -> anyhow::Result<T> {
+        match (self, format) {
+            (TextKey::Path(path), KeyFormat::Raw) => T::load(path),
+            _ => from_bytes(&self.read(format)?),
+        }
+    }
+    /// the key bytes; `format` says how a key file is encoded, literals are always base64
+    fn read(&self, format: KeyFormat) -> anyhow::Result<Vec<u8>> {
+        match self {
+            TextKey::Path(path) => {
+                let content = fs::read(path)?;
+                match format {
+                    KeyFormat::Raw => Ok(content),
+                    KeyFormat::Base64 => decode_key(&String::from_utf8_lossy(&content))
+                        .with_context(|| format!("Key file {} is not valid base64", path)),
+                    KeyFormat::Hex => hex::decode(String::from_utf8_lossy(&content).trim())
+                        .with_context(|| format!("Key file {} is not valid hex", path)),
+                }
+            }
+            TextKey::Literal(literal) => {
+                decode_key(literal).context("Key literal is not valid base64")
+            }
+            TextKey::Env(var) => {
+                let value = std::env::var(var)
+                    .with_context(|| format!("Environment variable {} is not set", var))?;
+                decode_key(&value)
+                    .with_context(|| format!("Environment variable {} is not valid base64", var))
+            }
+        }
+    }
 }
 
 impl Ed25519Signer {
     pub fn new(key: SigningKey) -> Self {
         Self { key }
     }
+    /// a raw 32-byte secret key, or a PKCS#8 `PRIVATE KEY` in PEM form; a key protected by
+    /// `text generate --protect` is decrypted with the passphrase in `RCLI_KEY_PASSPHRASE`
     pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
-        let key = SigningKey::from_bytes(key.try_into()?);
+        if key.starts_with(PROTECTED_KEY_MAGIC) {
+            let passphrase = key_passphrase().context("The key is passphrase-protected")?;
+            return Self::try_new_with_passphrase(key, &passphrase);
+        }
+        let key = if key.starts_with(PEM_PREFIX) {
+            let pem = std::str::from_utf8(key)?;
+            SigningKey::from_pkcs8_pem(pem).context("Invalid PKCS#8 Ed25519 private key")?
+        } else {
+            SigningKey::from_bytes(key.try_into()?)
+        };
         let signer = Ed25519Signer::new(key);
         Ok(signer)
     }
 }
 
+impl Ed25519Signer {
+    /// decrypt a key from `process_text_protect_key` and load it
+    pub fn try_new_with_passphrase(key: &[u8], passphrase: &str) -> anyhow::Result<Self> {
+        let secret = unprotect_key(key, passphrase)?;
+        Self::try_new(&secret)
+    }
+}
+
 impl Ed25519Verifier {
     pub fn new(key: VerifyingKey) -> Self {
         Self { key }
     }
+    /// a raw 32-byte public key, or a `PUBLIC KEY` in PEM form
     pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
-        let key = VerifyingKey::from_bytes(key.try_into()?)?;
+        let key = if key.starts_with(PEM_PREFIX) {
+            let pem = std::str::from_utf8(key)?;
+            VerifyingKey::from_public_key_pem(pem).context("Invalid Ed25519 public key")?
+        } else {
+            VerifyingKey::from_bytes(key.try_into()?)?
+        };
         let verifier = Ed25519Verifier::new(key);
         Ok(verifier)
     }
 }
 
+impl Secp256k1Signer {
+    pub fn new(key: k256::ecdsa::SigningKey) -> Self {
+        Self { key }
+    }
+    /// a raw 32-byte secret scalar
+    pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
+        let key = k256::ecdsa::SigningKey::from_slice(key)
+            .map_err(|_| anyhow::anyhow!("Invalid secp256k1 secret key"))?;
+        Ok(Secp256k1Signer::new(key))
+    }
+}
+
+impl Secp256k1Verifier {
+    pub fn new(key: k256::ecdsa::VerifyingKey) -> Self {
+        Self { key }
+    }
+    /// a SEC1-encoded public key, compressed (33 bytes) or uncompressed (65 bytes)
+    pub fn try_new(key: &[u8]) -> anyhow::Result<Self> {
+        let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+            .map_err(|_| anyhow::anyhow!("Invalid secp256k1 public key"))?;
+        Ok(Secp256k1Verifier::new(key))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -212,6 +804,404 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_blake3_verify_short_signature() -> anyhow::Result<()> {
+        let blake3 = Blake3::load("fixtures/blake3.txt")?;
+        let data = b"hello world";
+        let signature = blake3.sign(&mut &data[..])?;
+        assert!(!blake3.verify(&mut &data[..], &signature[..16])?);
+        assert!(!blake3.verify(&mut &data[..], &[])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blake3_xof_length() -> anyhow::Result<()> {
+        let key = TextKey::Path("fixtures/blake3.txt");
+        let long = SignOptions {
+            blake3_len: 64,
+            ..Default::default()
+        };
+        let input = "fixtures/lines.txt";
+        let sig = process_text_sign(input, key, TextSignFormat::Blake3, long)?;
+        assert_eq!(URL_SAFE_NO_PAD.decode(&sig)?.len(), 64);
+        assert!(process_text_verify(
+            input,
+            key,
+            TextSignFormat::Blake3,
+            &sig,
+            long
+        )?);
+        let default = SignOptions::default();
+        assert!(!process_text_verify(
+            input,
+            key,
+            TextSignFormat::Blake3,
+            &sig,
+            default
+        )?);
+
+        // a 32-byte MAC is unchanged from the plain keyed hash
+        let sig = process_text_sign(input, key, TextSignFormat::Blake3, default)?;
+        let blake3 = Blake3::load("fixtures/blake3.txt")?;
+        let hash = blake3::keyed_hash(&blake3.key, &fs::read(input)?);
+        assert_eq!(URL_SAFE_NO_PAD.decode(&sig)?, hash.as_bytes());
+        let short = SignOptions {
+            blake3_len: 8,
+            ..Default::default()
+        };
+        assert!(process_text_sign(input, key, TextSignFormat::Blake3, short).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hex_sig_encoding() -> anyhow::Result<()> {
+        let hex = SignOptions {
+            sig_encoding: SigEncoding::Hex,
+            ..Default::default()
+        };
+        let input = "fixtures/lines.txt";
+        let sk = TextKey::Path("fixtures/ed25519.sk");
+        let pk = TextKey::Path("fixtures/ed25519.pk");
+        let sig = process_text_sign(input, sk, TextSignFormat::Ed25519, hex)?;
+        assert_eq!(sig.len(), 128);
+        assert!(sig.bytes().all(|c| c.is_ascii_hexdigit()));
+        assert!(process_text_verify(
+            input,
+            pk,
+            TextSignFormat::Ed25519,
+            &sig,
+            hex
+        )?);
+
+        let base64 = SignOptions::default();
+        let result = process_text_verify(input, pk, TextSignFormat::Ed25519, &sig, base64);
+        assert!(!matches!(result, Ok(true)));
+        assert!(process_text_verify(input, pk, TextSignFormat::Ed25519, "zz", hex).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_format() -> anyhow::Result<()> {
+        let raw = fs::read("fixtures/blake3.txt")?;
+        let raw = trim_key_file(&raw, BLAKE3_KEY_LEN);
+        let dir = std::env::temp_dir();
+        let base64_key = dir.join("rcli-blake3.b64");
+        fs::write(&base64_key, format!("{}\n", STANDARD.encode(raw)))?;
+        let hex_key = dir.join("rcli-blake3.hex");
+        fs::write(&hex_key, hex::encode(raw))?;
+
+        let input = "fixtures/lines.txt";
+        let format = TextSignFormat::Blake3;
+        let expected = process_text_sign(
+            input,
+            TextKey::Path("fixtures/blake3.txt"),
+            format,
+            SignOptions::default(),
+        )?;
+        for (path, key_format) in [(&base64_key, KeyFormat::Base64), (&hex_key, KeyFormat::Hex)] {
+            let opts = SignOptions {
+                key_format,
+                ..Default::default()
+            };
+            let key = TextKey::Path(path.to_str().unwrap());
+            assert_eq!(process_text_sign(input, key, format, opts)?, expected);
+        }
+
+        // the base64 text itself is 44 bytes, not a valid raw key
+        let key = TextKey::Path(base64_key.to_str().unwrap());
+        assert!(process_text_sign(input, key, format, SignOptions::default()).is_err());
+        let opts = SignOptions {
+            key_format: KeyFormat::Hex,
+            ..Default::default()
+        };
+        let err = process_text_sign(input, key, format, opts).unwrap_err();
+        assert!(err.to_string().contains("not valid hex"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_blake3_key_length() -> anyhow::Result<()> {
+        assert!(Blake3::try_new(&[7u8; 32]).is_ok());
+        assert!(Blake3::try_new(b"0123456789abcdef0123456789abcdef\n").is_err());
+        let err = Blake3::try_new(b"too short").err().unwrap();
+        assert!(err.to_string().contains("exactly 32 bytes"));
+        assert!(Blake3::try_new(&[7u8; 64]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blake3_key_ending_in_newline() -> anyhow::Result<()> {
+        let mut key = (0u8..32).collect::<Vec<_>>();
+        key[31] = b'\n';
+        let literal = STANDARD.encode(&key);
+        let from_literal = process_text_sign(
+            "fixtures/lines.txt",
+            TextKey::Literal(&literal),
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+
+        let path = std::env::temp_dir().join("rcli-blake3-newline.key");
+        fs::write(&path, &key)?;
+        let path = path.to_string_lossy();
+        let from_file = process_text_sign(
+            "fixtures/lines.txt",
+            TextKey::Path(&path),
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        assert_eq!(from_literal, from_file);
+        let expected = Blake3::new(key.try_into().unwrap()).sign(&mut &b"hello"[..])?;
+        let signer = TextKey::Literal(&literal).load(KeyFormat::Raw, Blake3::try_new)?;
+        assert_eq!(signer.sign(&mut &b"hello"[..])?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_blake3_derive_key() -> anyhow::Result<()> {
+        let data = b"hello world";
+        let short = Blake3::derive(b"too short");
+        let long = Blake3::derive(&[7u8; 64]);
+        let signature = short.sign(&mut &data[..])?;
+        assert!(short.verify(&mut &data[..], &signature)?);
+        assert!(!long.verify(&mut &data[..], &signature)?);
+        assert_eq!(
+            Blake3::derive(b"too short").key,
+            blake3::derive_key(BLAKE3_KEY_CONTEXT, b"too short")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_fingerprint() -> anyhow::Result<()> {
+        let fingerprint = process_text_key_fingerprint(
+            TextKey::Path("fixtures/ed25519.pk"),
+            TextSignFormat::Ed25519,
+            SignOptions::default(),
+        )?;
+        let pk = fs::read("fixtures/ed25519.pk")?;
+        let expected = URL_SAFE_NO_PAD.encode(Sha256::digest(pk));
+        assert_eq!(fingerprint, format!("SHA256:{}", expected));
+
+        let fingerprint = process_text_key_fingerprint(
+            TextKey::Path("fixtures/blake3.txt"),
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        let key = fs::read("fixtures/blake3.txt")?;
+        let expected = URL_SAFE_NO_PAD.encode(Sha256::digest(&key[..32]));
+        assert_eq!(fingerprint, format!("SHA256:{}", expected));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_with_key_literal() -> anyhow::Result<()> {
+        let key = STANDARD.encode(&fs::read("fixtures/blake3.txt")?[..32]);
+        let key = TextKey::try_new(None, Some(&key))?;
+        let path = TextKey::Path("fixtures/blake3.txt");
+        let signed = process_text_sign(
+            "fixtures/lines.txt",
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        let expected = process_text_sign(
+            "fixtures/lines.txt",
+            path,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        assert_eq!(signed, expected);
+
+        let key = STANDARD.encode(fs::read("fixtures/ed25519.sk")?);
+        let signed = process_text_sign(
+            "fixtures/lines.txt",
+            TextKey::Literal(&key),
+            TextSignFormat::Ed25519,
+            SignOptions::default(),
+        )?;
+        let verified = process_text_verify(
+            "fixtures/lines.txt",
+            TextKey::Path("fixtures/ed25519.pk"),
+            TextSignFormat::Ed25519,
+            &signed,
+            SignOptions::default(),
+        )?;
+        assert!(verified);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_verify_manifest() -> anyhow::Result<()> {
+        let key = TextKey::Path("fixtures/blake3.txt");
+        let inputs = [
+            "fixtures/lines.txt".to_string(),
+            "fixtures/b64.txt".to_string(),
+        ];
+        let manifest = process_text_sign_manifest(
+            &inputs,
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        assert_eq!(manifest.lines().count(), 2);
+        assert!(manifest.lines().all(|l| l.contains("  fixtures/")));
+
+        let path = std::env::temp_dir().join("rcli-manifest.sig");
+        fs::write(&path, &manifest)?;
+        let results = process_text_verify_manifest(
+            &path.to_string_lossy(),
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        assert_eq!(
+            results,
+            [
+                ("fixtures/lines.txt".to_string(), Ok(true)),
+                ("fixtures/b64.txt".to_string(), Ok(true))
+            ]
+        );
+
+        let tampered = manifest.replacen("fixtures/lines.txt", "fixtures/malformed.csv", 1);
+        fs::write(&path, tampered)?;
+        let results = process_text_verify_manifest(
+            &path.to_string_lossy(),
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        assert_eq!(results[0].1, Ok(false));
+        assert_eq!(results[1].1, Ok(true));
+
+        let missing = manifest.replacen("fixtures/lines.txt", "fixtures/no-such-file.txt", 1);
+        fs::write(&path, missing)?;
+        let results = process_text_verify_manifest(
+            &path.to_string_lossy(),
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        let reason = results[0].1.as_ref().unwrap_err();
+        assert!(reason.contains("fixtures/no-such-file.txt"), "{}", reason);
+        assert_eq!(results[1].1, Ok(true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_with_env_key() -> anyhow::Result<()> {
+        std::env::set_var(
+            "RCLI_KEY",
+            STANDARD.encode(&fs::read("fixtures/blake3.txt")?[..32]),
+        );
+        let key = TextKey::try_new(Some("env:RCLI_KEY"), None)?;
+        let signed = process_text_sign(
+            "fixtures/lines.txt",
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        let path = TextKey::Path("fixtures/blake3.txt");
+        let expected = process_text_sign(
+            "fixtures/lines.txt",
+            path,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )?;
+        assert_eq!(signed, expected);
+
+        let key = TextKey::try_new(Some("env:RCLI_KEY_UNSET"), None)?;
+        let err = process_text_sign(
+            "fixtures/lines.txt",
+            key,
+            TextSignFormat::Blake3,
+            SignOptions::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("RCLI_KEY_UNSET"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_verify_timestamped() -> anyhow::Result<()> {
+        let sk = TextKey::Path("fixtures/ed25519.sk");
+        let pk = TextKey::Path("fixtures/ed25519.pk");
+        let format = TextSignFormat::Ed25519;
+        let signed = process_text_sign_timestamped(
+            "fixtures/lines.txt",
+            sk,
+            format,
+            SignOptions::default(),
+        )?;
+        let verify = |input, sig: &str| {
+            process_text_verify_timestamped(input, pk, format, sig, SignOptions::default(), 60)
+        };
+        assert!(verify("fixtures/lines.txt", &signed)?);
+        assert!(!verify("fixtures/b64.txt", &signed)?);
+
+        let message = fs::read("fixtures/lines.txt")?;
+        let old = sign_at(
+            &mut message.as_slice(),
+            sk,
+            format,
+            SignOptions::default(),
+            unix_now() - 3600,
+        )?;
+        let err = verify("fixtures/lines.txt", &URL_SAFE_NO_PAD.encode(&old)).unwrap_err();
+        assert!(err.to_string().contains("allowed 60s"), "{}", err);
+
+        let future = sign_at(
+            &mut message.as_slice(),
+            sk,
+            format,
+            SignOptions::default(),
+            unix_now() + 3600,
+        )?;
+        let err = verify("fixtures/lines.txt", &URL_SAFE_NO_PAD.encode(&future)).unwrap_err();
+        assert!(err.to_string().contains("in the future"), "{}", err);
+        let skewed = sign_at(
+            &mut message.as_slice(),
+            sk,
+            format,
+            SignOptions::default(),
+            unix_now() + 5,
+        )?;
+        assert!(verify(
+            "fixtures/lines.txt",
+            &URL_SAFE_NO_PAD.encode(&skewed)
+        )?);
+
+        // moving the timestamp breaks the signature rather than passing the age check
+        let mut moved = old.clone();
+        moved[..8].copy_from_slice(&unix_now().to_be_bytes());
+        assert!(!verify(
+            "fixtures/lines.txt",
+            &URL_SAFE_NO_PAD.encode(&moved)
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_passphrase_protected_ed25519_key() -> anyhow::Result<()> {
+        let key = process_text_generate(TextSignFormat::Ed25519)?;
+        let protected = process_text_protect_key(TextSignFormat::Ed25519, &key[0], "hunter2")?;
+        let signer = Ed25519Signer::try_new_with_passphrase(&protected, "hunter2")?;
+        let verifier = Ed25519Verifier::try_new(&key[1])?;
+        let sig = signer.sign(&mut &b"hello"[..])?;
+        assert!(verifier.verify(&b"hello"[..], &sig)?);
+
+        let err = Ed25519Signer::try_new_with_passphrase(&protected, "hunter3").err();
+        assert!(format!("{:?}", err).contains("Wrong passphrase"));
+        assert!(process_text_protect_key(TextSignFormat::Blake3, &key[0], "hunter2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_key_requires_exactly_one() {
+        assert!(TextKey::try_new(None, None).is_err());
+        assert!(TextKey::try_new(Some("fixtures/blake3.txt"), Some("a2V5")).is_err());
+    }
+
     #[test]
     fn test_ed25519_sign_verify() -> anyhow::Result<()> {
         let sk = Ed25519Signer::load("fixtures/ed25519.sk")?;
@@ -221,4 +1211,105 @@ mod tests {
         assert!(pk.verify(&mut &data[..], &signature).unwrap());
         Ok(())
     }
+
+    #[test]
+    fn test_ed25519_embedded_round_trip() -> anyhow::Result<()> {
+        let blob = process_text_sign_embedded(
+            "fixtures/lines.txt",
+            TextKey::Path("fixtures/ed25519.sk"),
+            TextSignFormat::Ed25519,
+            SignOptions::default(),
+        )?;
+        let path = std::env::temp_dir().join("rcli-lines.embedded");
+        fs::write(&path, &blob)?;
+        let (message, verified) = process_text_verify_embedded(
+            &path.to_string_lossy(),
+            TextKey::Path("fixtures/ed25519.pk"),
+            TextSignFormat::Ed25519,
+            SignOptions::default(),
+        )?;
+        assert!(verified);
+        assert_eq!(message, fs::read("fixtures/lines.txt")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ed25519_embedded_tampered() -> anyhow::Result<()> {
+        let blob = process_text_sign_embedded(
+            "fixtures/lines.txt",
+            TextKey::Path("fixtures/ed25519.sk"),
+            TextSignFormat::Ed25519,
+            SignOptions::default(),
+        )?;
+        let path = std::env::temp_dir().join("rcli-lines-tampered.embedded");
+        let verify = |blob: &[u8]| -> anyhow::Result<bool> {
+            fs::write(&path, URL_SAFE_NO_PAD.encode(blob))?;
+            let (_, verified) = process_text_verify_embedded(
+                &path.to_string_lossy(),
+                TextKey::Path("fixtures/ed25519.pk"),
+                TextSignFormat::Ed25519,
+                SignOptions::default(),
+            )?;
+            Ok(verified)
+        };
+
+        let mut blob = URL_SAFE_NO_PAD.decode(blob)?;
+        blob[4] ^= 1;
+        assert!(!verify(&blob)?);
+        blob[4] ^= 1;
+        blob.pop();
+        assert!(!verify(&blob)?);
+        assert!(verify(&blob[..2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ed25519_pem_keys() -> anyhow::Result<()> {
+        let sk = Ed25519Signer::load("fixtures/ed25519.sk.pem")?;
+        let pk = Ed25519Verifier::load("fixtures/ed25519.pk.pem")?;
+        assert_eq!(
+            sk.key.to_bytes(),
+            Ed25519Signer::load("fixtures/ed25519.sk")?.key.to_bytes()
+        );
+        assert_eq!(pk.key, Ed25519Verifier::load("fixtures/ed25519.pk")?.key);
+
+        let data = b"hello world";
+        let signature = sk.sign(&mut &data[..])?;
+        assert!(pk.verify(&mut &data[..], &signature)?);
+        assert!(Ed25519Verifier::try_new(b"-----BEGIN PUBLIC KEY-----\ngarbage\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_secp256k1_sign_verify() -> anyhow::Result<()> {
+        let sk = Secp256k1Signer::load("fixtures/secp256k1.sk")?;
+        let pk = Secp256k1Verifier::load("fixtures/secp256k1.pk")?;
+        let data = b"hello world";
+        let signature = sk.sign(&mut &data[..])?;
+        assert_eq!(signature.len(), 64);
+        assert!(pk.verify(&mut &data[..], &signature)?);
+        assert!(!pk.verify(&mut &b"hello world!"[..], &signature)?);
+        assert!(!pk.verify(&mut &data[..], &signature[..32])?);
+
+        let key = Secp256k1Signer::generate()?;
+        assert_eq!((key[0].len(), key[1].len()), (32, 33));
+        Ok(())
+    }
+
+    #[test]
+    fn test_secp256k1_known_answer() -> anyhow::Result<()> {
+        // secret key 1 signing "Satoshi Nakamoto", the well-known RFC 6979 secp256k1 vector
+        let mut sk = [0u8; 32];
+        sk[31] = 1;
+        let signer = Secp256k1Signer::try_new(&sk)?;
+        let signature = signer.sign(&mut &b"Satoshi Nakamoto"[..])?;
+        let expected = "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8\
+                        2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5";
+        let expected = (0..expected.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&expected[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(signature, expected);
+        Ok(())
+    }
 }
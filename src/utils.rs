@@ -1,10 +1,348 @@
-use std::{fs::File, io::Read};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+    time::{Duration, Instant},
+};
 
+static TIMING: AtomicBool = AtomicBool::new(false);
+static NO_CLOBBER: AtomicBool = AtomicBool::new(false);
+/// 0 detects gzip from the magic bytes, 1 always decompresses, 2 never does
+static GZIP: AtomicU8 = AtomicU8::new(0);
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// stdin for `-`, a URL or a file, byte for byte
 pub fn get_reader(input: &str) -> anyhow::Result<Box<dyn Read>> {
     let reader = if input == "-" {
         Box::new(std::io::stdin()) as Box<dyn Read>
+    } else if is_url(input) {
+        open_url(input)?
     } else {
         Box::new(File::open(input)?)
     };
     Ok(reader)
 }
+
+/// like `get_reader`, but gunzips the input; for the text pipelines (csv, lines) that
+/// want the content rather than the exact bytes
+pub fn get_reader_decompressed(input: &str) -> anyhow::Result<Box<dyn Read>> {
+    gunzip(get_reader(input)?)
+}
+
+/// decompress gzip input transparently, detected from its magic bytes unless overridden
+fn gunzip(reader: Box<dyn Read>) -> anyhow::Result<Box<dyn Read>> {
+    let mut reader = BufReader::new(reader);
+    let gzip = match GZIP.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => reader.fill_buf()?.starts_with(&GZIP_MAGIC),
+    };
+    if gzip {
+        Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// force (`Some(true)`) or disable (`Some(false)`) gzip decompression in
+/// `get_reader_decompressed`, set from the global `--gzip`/`--no-gzip` flags; `None`
+/// detects it
+pub fn set_gzip(gzip: Option<bool>) {
+    let mode = match gzip {
+        None => 0,
+        Some(true) => 1,
+        Some(false) => 2,
+    };
+    GZIP.store(mode, Ordering::Relaxed);
+}
+
+/// stdout for `-`, otherwise the file, created or truncated; under `--no-clobber` an
+/// existing file is an error instead
+pub fn get_writer(output: &str) -> anyhow::Result<Box<dyn Write>> {
+    let writer = if output == "-" {
+        Box::new(std::io::stdout()) as Box<dyn Write>
+    } else if NO_CLOBBER.load(Ordering::Relaxed) {
+        let file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(output)
+            .map_err(|e| clobber_error(e, Path::new(output)))?;
+        Box::new(file)
+    } else {
+        Box::new(File::create(output)?)
+    };
+    Ok(writer)
+}
+
+/// refuse to overwrite existing output files in `get_writer` and `write_atomic`, set from
+/// the global `--no-clobber` flag
+pub fn set_no_clobber(no_clobber: bool) {
+    NO_CLOBBER.store(no_clobber, Ordering::Relaxed);
+}
+
+fn clobber_error(e: io::Error, output: &Path) -> anyhow::Error {
+    if e.kind() == io::ErrorKind::AlreadyExists {
+        anyhow::anyhow!(
+            "{} already exists and --no-clobber is set",
+            output.display()
+        )
+    } else {
+        e.into()
+    }
+}
+
+/// write a file through a temporary sibling that is renamed into place once `write`
+/// succeeds, so the output is either complete or untouched, never truncated
+pub fn write_atomic(
+    output: impl AsRef<Path>,
+    write: impl FnOnce(&mut dyn Write) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let output = output.as_ref();
+    let name = output
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a file path", output.display()))?;
+    let tmp = output.with_file_name(format!(
+        ".{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    let written = (|| -> anyhow::Result<()> {
+        let mut file = io::BufWriter::new(File::create(&tmp)?);
+        write(&mut file)?;
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        if NO_CLOBBER.load(Ordering::Relaxed) {
+            // unlike rename, linking fails if the output already exists
+            fs::hard_link(&tmp, output).map_err(|e| clobber_error(e, output))?;
+            fs::remove_file(&tmp)?;
+            return Ok(());
+        }
+        match fs::rename(&tmp, output) {
+            // a temporary in the same directory shouldn't cross devices, but some mounts
+            // (e.g. bind-mounted files) refuse the rename anyway
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                fs::copy(&tmp, output)?;
+                fs::remove_file(&tmp)?;
+                Ok(())
+            }
+            res => Ok(res?),
+        }
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// whether `get_reader` would fetch the input over HTTP(S) instead of opening a file
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+#[cfg(feature = "url")]
+fn open_url(url: &str) -> anyhow::Result<Box<dyn Read>> {
+    use anyhow::Context;
+
+    // reqwest is built without a crypto provider of its own; use the aws-lc-rs one the
+    // HTTPS server already links. Installing fails harmlessly if one is already set.
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let fetch = || -> anyhow::Result<Vec<u8>> {
+        let res =
+            reqwest::blocking::get(url).with_context(|| format!("Failed to fetch {}", url))?;
+        let status = res.status();
+        if !status.is_success() {
+            anyhow::bail!("Failed to fetch {}: HTTP {}", url, status);
+        }
+        Ok(res.bytes()?.to_vec())
+    };
+    // the blocking client owns a tokio runtime, which can't be created or dropped on a
+    // thread already driving one (e.g. inside `execute`), so fetch on a thread of its own
+    let body = std::thread::scope(|s| s.spawn(fetch).join())
+        .map_err(|_| anyhow::anyhow!("Fetching {} panicked", url))??;
+    Ok(Box::new(std::io::Cursor::new(body)))
+}
+
+#[cfg(not(feature = "url"))]
+fn open_url(url: &str) -> anyhow::Result<Box<dyn Read>> {
+    anyhow::bail!(
+        "Cannot read {}: rcli was built without the `url` feature",
+        url
+    )
+}
+
+/// what `--dry-run` reports instead of writing `content` to `output`
+pub fn dry_run_note(output: &str, content: &[u8]) -> String {
+    let target = if output == "-" { "stdout" } else { output };
+    let note = format!("would write {} bytes to {}", content.len(), target);
+    match std::str::from_utf8(content)
+        .ok()
+        .and_then(|s| s.lines().next())
+    {
+        Some(line) => format!("{}: {}", note, line),
+        None => note,
+    }
+}
+
+/// name, version and the git sha the binary was built from, for `--version-json`
+pub fn version_json() -> String {
+    serde_json::json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": env!("RCLI_GIT_SHA"),
+    })
+    .to_string()
+}
+
+/// turn on the per-phase report of `Timings::report`, set from the global `--timing` flag
+pub fn enable_timing() {
+    TIMING.store(true, Ordering::Relaxed);
+}
+
+/// time spent in each phase of a command, in the order the phases ran
+#[derive(Debug, Default)]
+pub struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn time<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let ret = f();
+        self.phases.push((phase, start.elapsed()));
+        ret
+    }
+
+    /// print the phases to stderr if `--timing` is on
+    pub fn report(&self) {
+        if TIMING.load(Ordering::Relaxed) {
+            eprint!("{}", self);
+        }
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (phase, elapsed) in &self.phases {
+            writeln!(f, "{:<10} {:?}", phase, elapsed)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("rcli-atomic");
+        fs::create_dir_all(&dir)?;
+        let output = dir.join("output.json");
+        let _ = fs::remove_file(&output);
+
+        let failed = write_atomic(&output, |w| {
+            w.write_all(b"[{\"partial\": ")?;
+            anyhow::bail!("conversion failed")
+        });
+        assert!(failed.is_err());
+        assert!(!output.exists());
+        assert_eq!(fs::read_dir(&dir)?.count(), 0);
+
+        write_atomic(&output, |w| Ok(w.write_all(b"[]")?))?;
+        assert_eq!(fs::read_to_string(&output)?, "[]");
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_get_reader_url() -> anyhow::Result<()> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request_line = String::new();
+                BufReader::new(&stream)
+                    .read_line(&mut request_line)
+                    .unwrap();
+                let res = if request_line.starts_with("GET /data.csv ") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 8\r\nConnection: close\r\n\r\na,b\n1,2\n"
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                stream.write_all(res.as_bytes()).unwrap();
+            }
+        });
+
+        let mut content = String::new();
+        get_reader(&format!("http://{}/data.csv", addr))?.read_to_string(&mut content)?;
+        assert_eq!(content, "a,b\n1,2\n");
+
+        let err = get_reader(&format!("http://{}/missing.csv", addr))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("404"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_writer() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("rcli-get-writer.txt");
+        std::fs::write(&path, "previous content that is longer")?;
+        let mut writer = get_writer(&path.to_string_lossy())?;
+        writer.write_all(b"hello")?;
+        drop(writer);
+        assert_eq!(std::fs::read_to_string(&path)?, "hello");
+
+        let mut writer = get_writer("-")?;
+        writer.write_all(b"")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_reader_gzip() -> anyhow::Result<()> {
+        let mut plain = String::new();
+        get_reader_decompressed("fixtures/juventus.csv.gz")?.read_to_string(&mut plain)?;
+        assert_eq!(plain, std::fs::read_to_string("assets/juventus.csv")?);
+
+        let mut raw = Vec::new();
+        get_reader("fixtures/juventus.csv.gz")?.read_to_end(&mut raw)?;
+        assert_eq!(raw, std::fs::read("fixtures/juventus.csv.gz")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/data.csv"));
+        assert!(is_url("http://localhost:8080/"));
+        assert!(!is_url("assets/juventus.csv"));
+        assert!(!is_url("-"));
+    }
+
+    #[test]
+    fn test_version_json() -> anyhow::Result<()> {
+        let v: serde_json::Value = serde_json::from_str(&version_json())?;
+        assert_eq!(v["name"], "rcli");
+        assert_eq!(v["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!v["git_sha"].as_str().unwrap_or_default().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_note() {
+        assert_eq!(
+            dry_run_note("out.json", b"[\n  {}\n]"),
+            "would write 8 bytes to out.json: ["
+        );
+        assert_eq!(
+            dry_run_note("-", &[0xff, 0]),
+            "would write 2 bytes to stdout"
+        );
+    }
+}
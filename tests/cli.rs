@@ -0,0 +1,263 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+fn rcli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rcli"))
+        .args(args)
+        .output()
+        .expect("failed to run rcli")
+}
+
+#[test]
+fn test_verify_exit_code() {
+    let out = rcli(&[
+        "text",
+        "sign",
+        "-k",
+        "fixtures/blake3.txt",
+        "-i",
+        "fixtures/lines.txt",
+    ]);
+    assert!(out.status.success());
+    let sig = String::from_utf8(out.stdout).unwrap();
+    let sig = format!("--sig={}", sig.trim());
+
+    let verify = |input| {
+        rcli(&[
+            "text",
+            "verify",
+            "-k",
+            "fixtures/blake3.txt",
+            "-i",
+            input,
+            &sig,
+        ])
+    };
+    let out = verify("fixtures/lines.txt");
+    assert!(out.status.success(), "{:?}", out);
+
+    let out = verify("fixtures/b64.txt");
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("Signature verification failed"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_verify_sig_from_stdin() {
+    let out = rcli(&[
+        "text",
+        "sign",
+        "-k",
+        "fixtures/blake3.txt",
+        "-i",
+        "fixtures/lines.txt",
+    ]);
+    assert!(out.status.success());
+
+    let verify = |input: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rcli"))
+            .args(["text", "verify", "-k", "fixtures/blake3.txt"])
+            .args(["-i", input, "--sig", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("failed to run rcli");
+        // rcli may refuse and exit before reading the signature
+        let _ = child.stdin.take().unwrap().write_all(&out.stdout);
+        child.wait_with_output().unwrap()
+    };
+    let verified = verify("fixtures/lines.txt");
+    assert!(verified.status.success(), "{:?}", verified);
+    assert_eq!(String::from_utf8_lossy(&verified.stdout).trim(), "true");
+
+    let both_stdin = verify("-");
+    assert!(!both_stdin.status.success());
+    let stderr = String::from_utf8_lossy(&both_stdin.stderr);
+    assert!(stderr.contains("--input must be a file"), "{}", stderr);
+}
+
+#[test]
+fn test_error_chain_on_stderr() {
+    let out = rcli(&["csv", "-i", "fixtures/malformed.csv", "-o", "-"]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("Failed to parse record 2"), "{}", stderr);
+    assert!(stderr.contains("Caused by:"), "{}", stderr);
+}
+
+#[test]
+fn test_csv_tab_delimiter() {
+    let out = rcli(&["csv", "-i", "fixtures/people.tsv", "-d", "tab", "-o", "-"]);
+    assert!(out.status.success(), "{:?}", out);
+    let records: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(
+        records,
+        serde_json::json!([{"name": "alice", "age": "30"}, {"name": "bob", "age": "25"}])
+    );
+}
+
+#[test]
+fn test_no_clobber() {
+    let dir = std::env::temp_dir().join("rcli-no-clobber");
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("out.json");
+    let output = output.to_str().unwrap();
+    std::fs::write(output, "keep me").unwrap();
+
+    let csv = |extra: &[&str]| {
+        let mut args = vec!["csv", "-i", "assets/juventus.csv", "-o", output];
+        args.extend(extra);
+        rcli(&args)
+    };
+    let out = csv(&["--no-clobber"]);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("already exists"), "{}", stderr);
+    assert_eq!(std::fs::read_to_string(output).unwrap(), "keep me");
+
+    let out = rcli(&[
+        "base64",
+        "encode",
+        "-i",
+        "fixtures/lines.txt",
+        "-o",
+        output,
+        "--no-clobber",
+    ]);
+    assert!(!out.status.success());
+    assert_eq!(std::fs::read_to_string(output).unwrap(), "keep me");
+
+    let report = dir.join("errors.jsonl");
+    std::fs::write(&report, "keep me").unwrap();
+    let out = rcli(&[
+        "csv",
+        "-i",
+        "fixtures/ragged.csv",
+        "-o",
+        "-",
+        "--flexible",
+        "--error-report",
+        report.to_str().unwrap(),
+        "--no-clobber",
+    ]);
+    assert!(!out.status.success());
+    assert_eq!(std::fs::read_to_string(&report).unwrap(), "keep me");
+
+    // overwriting stays the default
+    assert!(csv(&[]).status.success());
+    assert_ne!(std::fs::read_to_string(output).unwrap(), "keep me");
+}
+
+#[test]
+fn test_error_report_dry_run() {
+    let report = std::env::temp_dir().join("rcli-dry-run-errors.jsonl");
+    let _ = std::fs::remove_file(&report);
+    let out = rcli(&[
+        "csv",
+        "-i",
+        "fixtures/ragged.csv",
+        "-o",
+        "-",
+        "--flexible",
+        "--error-report",
+        report.to_str().unwrap(),
+        "--dry-run",
+    ]);
+    assert!(out.status.success(), "{:?}", out);
+    assert!(!report.exists());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains(report.to_str().unwrap()), "{}", stderr);
+}
+
+#[test]
+fn test_no_clobber_encode_files() {
+    let dir = std::env::temp_dir().join("rcli-no-clobber-files");
+    std::fs::create_dir_all(&dir).unwrap();
+    let inputs = ["a.txt", "b.txt"].map(|name| dir.join(name));
+    for input in &inputs {
+        std::fs::write(input, "hello").unwrap();
+    }
+    let existing = dir.join("b.txt.b64");
+    std::fs::write(&existing, "keep me").unwrap();
+    let args = |no_clobber: bool| {
+        let mut args = vec!["base64", "encode", "-i"];
+        args.extend(inputs.iter().map(|p| p.to_str().unwrap()));
+        if no_clobber {
+            args.push("--no-clobber");
+        }
+        rcli(&args)
+    };
+
+    let out = args(true);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("already exists"), "{}", stderr);
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "keep me");
+
+    assert!(args(false).status.success());
+    assert_eq!(std::fs::read_to_string(&existing).unwrap(), "aGVsbG8=\n");
+}
+
+#[test]
+fn test_genpass_length_from_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rcli"))
+        .args(["genpass", "--length", "-", "--json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run rcli");
+    child.stdin.take().unwrap().write_all(b"20\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    let report: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(report["password"].as_str().unwrap().len(), 20);
+}
+
+#[test]
+fn test_generate_protected_key_from_env() {
+    let dir = std::env::temp_dir().join("rcli-protect");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.to_str().unwrap();
+    let run = |args: &[&str], passphrase: Option<&str>| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_rcli"));
+        cmd.args(args).env_remove("RCLI_KEY_PASSPHRASE");
+        if let Some(passphrase) = passphrase {
+            cmd.env("RCLI_KEY_PASSPHRASE", passphrase);
+        }
+        cmd.output().expect("failed to run rcli")
+    };
+
+    let generate = ["text", "generate", "-f", "ed25519", "-o", dir, "--protect"];
+    let out = run(&generate, None);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("RCLI_KEY_PASSPHRASE"));
+    assert!(run(&generate, Some("hunter2")).status.success());
+
+    let sk = format!("{}/ed25519.sk", dir);
+    let sign = [
+        "text",
+        "sign",
+        "--format",
+        "ed25519",
+        "-k",
+        &sk,
+        "-i",
+        "fixtures/lines.txt",
+    ];
+    assert!(run(&sign, Some("hunter2")).status.success());
+    assert!(!run(&sign, Some("hunter3")).status.success());
+    assert!(
+        !run(&["text", "generate", "-o", dir, "--passphrase", "x"], None)
+            .status
+            .success()
+    );
+}